@@ -0,0 +1,332 @@
+//! Generate per-field accessor traits, implemented by variant structs and the top-level enums.
+use crate::utils::{unboxed_ref, Boxing};
+use crate::{ErrorOpts, FieldData, GetterOpts, VariantKey};
+use quote::{format_ident, quote};
+use syn::{Generics, Ident, Visibility};
+
+/// A trait with accessor methods for one or more fields, set with `accessor_trait = ".."`.
+pub struct AccessorTrait<'a> {
+    pub name: &'a Ident,
+    pub fields: Vec<&'a FieldData>,
+}
+
+/// The type which an accessor trait is implemented for.
+#[derive(Clone, Copy)]
+pub enum AccessorImplFor {
+    /// A variant struct or the top-level enum, for which `inner` is a reference.
+    Owned,
+    /// The `Ref` type, for which `inner` is a reference to a reference.
+    Ref,
+    /// The `RefMut` type, for which `inner` is a reference to a mutable reference.
+    RefMut,
+}
+
+impl AccessorTrait<'_> {
+    /// Name of the subtrait with the `_mut` methods, like `HasSlotMut`.
+    pub fn mut_name(&self) -> Ident {
+        format_ident!("{}Mut", self.name)
+    }
+
+    /// Whether every field of this trait exists in all variants.
+    ///
+    /// If not, its methods return a `Result` like partial getters, so that it can be implemented
+    /// for the top-level enum.
+    pub fn is_common(&self) -> bool {
+        self.fields.iter().all(|field_data| field_data.is_common())
+    }
+
+    /// Whether every field of this trait exists in the variant struct for `variant_key`.
+    pub fn exists_in(&self, variant_key: &VariantKey) -> bool {
+        self.fields
+            .iter()
+            .all(|field_data| field_data.only_combinations.contains(variant_key))
+    }
+}
+
+/// Group the fields with accessor traits by trait name, in order of first appearance.
+pub fn group_accessor_traits(fields: &[FieldData]) -> Vec<AccessorTrait<'_>> {
+    let mut accessor_traits: Vec<AccessorTrait> = vec![];
+
+    for field_data in fields {
+        let Some(name) = &field_data.accessor_trait else {
+            continue;
+        };
+        if let Some(accessor_trait) = accessor_traits.iter_mut().find(|t| t.name == name) {
+            accessor_trait.fields.push(field_data);
+        } else {
+            accessor_traits.push(AccessorTrait {
+                name,
+                fields: vec![field_data],
+            });
+        }
+    }
+    accessor_traits
+}
+
+/// The getter options used for a field's accessor methods.
+fn accessor_opts(field_data: &FieldData) -> &GetterOpts {
+    if field_data.is_common() {
+        &field_data.getter_opts
+    } else {
+        &field_data.partial_getter_opts
+    }
+}
+
+/// The name, return type and field of an accessor method.
+struct AccessorMethod<'a> {
+    fn_name: Ident,
+    return_type: proc_macro2::TokenStream,
+    field_data: &'a FieldData,
+    copy: bool,
+}
+
+/// The methods of the read-only trait and the `_mut` subtrait for `accessor_trait`.
+fn accessor_methods<'a>(
+    accessor_trait: &AccessorTrait<'a>,
+    error_opts: &ErrorOpts,
+) -> (Vec<AccessorMethod<'a>>, Vec<AccessorMethod<'a>>) {
+    let is_common = accessor_trait.is_common();
+    let wrap = |ty: proc_macro2::TokenStream| {
+        if is_common {
+            ty
+        } else {
+            error_opts.build_result_type(ty).0
+        }
+    };
+
+    let mut methods = vec![];
+    let mut mut_methods = vec![];
+    for field_data in &accessor_trait.fields {
+        let field_type = &field_data.field.ty;
+        let getter_opts = accessor_opts(field_data);
+        let fn_name = getter_opts.rename.as_ref().unwrap_or(&field_data.name);
+
+        let return_type = if getter_opts.copy {
+            quote! { #field_type }
+        } else {
+            quote! { &#field_type }
+        };
+        methods.push(AccessorMethod {
+            fn_name: fn_name.clone(),
+            return_type: wrap(return_type),
+            field_data,
+            copy: getter_opts.copy,
+        });
+
+        if !getter_opts.no_mut {
+            mut_methods.push(AccessorMethod {
+                fn_name: format_ident!("{}_mut", fn_name),
+                return_type: wrap(quote! { &mut #field_type }),
+                field_data,
+                copy: false,
+            });
+        }
+    }
+    (methods, mut_methods)
+}
+
+/// Generate the definition of an accessor trait and its `_mut` subtrait, and implement them for
+/// each of `struct_names`.
+pub fn generate_accessor_trait(
+    accessor_trait: &AccessorTrait,
+    visibility: &Visibility,
+    generics: &Generics,
+    struct_names: &[Ident],
+    error_opts: &ErrorOpts,
+) -> proc_macro2::TokenStream {
+    let trait_name = accessor_trait.name;
+    let mut_trait_name = accessor_trait.mut_name();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (methods, mut_methods) = accessor_methods(accessor_trait, error_opts);
+
+    let signatures = |methods: &[AccessorMethod], self_arg| {
+        methods
+            .iter()
+            .map(|method| {
+                let fn_name = &method.fn_name;
+                let return_type = &method.return_type;
+                quote! { fn #fn_name(#self_arg) -> #return_type }
+            })
+            .collect::<Vec<_>>()
+    };
+    let bodies = |methods: &[AccessorMethod], reference| {
+        methods
+            .iter()
+            .map(|method| {
+                let field_name = &method.field_data.name;
+                let expr = if method.copy {
+                    quote! { self.#field_name }
+                } else {
+                    quote! { #reference self.#field_name }
+                };
+                if accessor_trait.is_common() {
+                    expr
+                } else {
+                    quote! { Ok(#expr) }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let read_signatures = signatures(&methods, quote! { &self });
+    let read_bodies = bodies(&methods, quote! { & });
+    let mut_signatures = signatures(&mut_methods, quote! { &mut self });
+    let mut_bodies = bodies(&mut_methods, quote! { &mut });
+
+    let impls = struct_names.iter().map(|struct_name| {
+        let mut_impl = (!mut_methods.is_empty()).then(|| {
+            quote! {
+                impl #impl_generics #mut_trait_name #ty_generics for #struct_name #ty_generics #where_clause {
+                    #(
+                        #mut_signatures {
+                            #mut_bodies
+                        }
+                    )*
+                }
+            }
+        });
+        quote! {
+            impl #impl_generics #trait_name #ty_generics for #struct_name #ty_generics #where_clause {
+                #(
+                    #read_signatures {
+                        #read_bodies
+                    }
+                )*
+            }
+
+            #mut_impl
+        }
+    });
+
+    let mut_trait = (!mut_methods.is_empty()).then(|| {
+        quote! {
+            #visibility trait #mut_trait_name #generics: #trait_name #ty_generics #where_clause {
+                #(
+                    #mut_signatures;
+                )*
+            }
+        }
+    });
+
+    quote! {
+        #visibility trait #trait_name #generics #where_clause {
+            #(
+                #read_signatures;
+            )*
+        }
+
+        #mut_trait
+
+        #(
+            #impls
+        )*
+    }
+}
+
+/// Implement an accessor trait for the top-level enum, `Ref` or `RefMut` by delegating to the
+/// implementations for its variants. The `_mut` subtrait is implemented too, except for `Ref`.
+///
+/// The `generics` are the generics of `type_name`, while `trait_generics` are the generics of the
+/// top-level enum, which are used for the trait.
+///
+/// The `variant_keys` are the variant structs (or inner enums if `is_meta`) of each variant. Those
+/// which don't implement a partial trait return the partial getter error.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_accessor_trait_impl_for_enum(
+    accessor_trait: &AccessorTrait,
+    type_name: &Ident,
+    generics: &Generics,
+    trait_generics: &Generics,
+    variant_names: &[Ident],
+    variant_keys: &[VariantKey],
+    is_meta: bool,
+    boxed: &[Boxing],
+    error_opts: &ErrorOpts,
+    impl_for: AccessorImplFor,
+) -> proc_macro2::TokenStream {
+    let trait_name = accessor_trait.name;
+    let mut_trait_name = accessor_trait.mut_name();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (_, trait_ty_generics, _) = trait_generics.split_for_impl();
+    let (methods, mut_methods) = accessor_methods(accessor_trait, error_opts);
+    let (_, err_expr) = error_opts.build_result_type(quote! { () });
+
+    let (inner, inner_mut): (Vec<_>, Vec<_>) = variant_names
+        .iter()
+        .zip(boxed)
+        .map(|(variant_name, boxing)| match impl_for {
+            AccessorImplFor::Owned => (
+                unboxed_ref(
+                    quote! { inner },
+                    *boxing,
+                    quote! { Self },
+                    variant_name,
+                    false,
+                ),
+                unboxed_ref(
+                    quote! { inner },
+                    *boxing,
+                    quote! { Self },
+                    variant_name,
+                    true,
+                ),
+            ),
+            AccessorImplFor::Ref => (quote! { *inner }, quote! { *inner }),
+            AccessorImplFor::RefMut => (quote! { &**inner }, quote! { &mut **inner }),
+        })
+        .unzip();
+    let implemented = variant_keys
+        .iter()
+        .map(|variant_key| is_meta || accessor_trait.exists_in(variant_key))
+        .collect::<Vec<_>>();
+
+    let method_impls = |methods: &[AccessorMethod], self_arg, inners: &[_], method_trait| {
+        methods
+            .iter()
+            .map(|method| {
+                let fn_name = &method.fn_name;
+                let return_type = &method.return_type;
+                let arms = variant_names.iter().zip(inners).zip(&implemented).map(
+                    |((variant_name, inner), implemented)| {
+                        if *implemented {
+                            quote! {
+                                Self::#variant_name(inner) => #method_trait::#fn_name(#inner),
+                            }
+                        } else {
+                            quote! { Self::#variant_name(_) => Err(#err_expr), }
+                        }
+                    },
+                );
+                quote! {
+                    fn #fn_name(#self_arg) -> #return_type {
+                        match self {
+                            #(#arms)*
+                        }
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let read_impls = method_impls(&methods, quote! { &self }, &inner, trait_name);
+
+    // The `Ref` type can't implement methods which return mutable references.
+    let mut_impl = (!mut_methods.is_empty() && !matches!(impl_for, AccessorImplFor::Ref)).then(|| {
+        let mut_impls = method_impls(&mut_methods, quote! { &mut self }, &inner_mut, &mut_trait_name);
+        quote! {
+            impl #impl_generics #mut_trait_name #trait_ty_generics for #type_name #ty_generics #where_clause {
+                #(
+                    #mut_impls
+                )*
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics #trait_name #trait_ty_generics for #type_name #ty_generics #where_clause {
+            #(
+                #read_impls
+            )*
+        }
+
+        #mut_impl
+    }
+}
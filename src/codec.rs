@@ -0,0 +1,104 @@
+//! Generate methods which encode and decode a superstruct using a codec trait implemented by each
+//! variant struct, with the `variant_codec` option.
+use crate::naming::generate_kind_enum_name;
+use crate::utils::with_variant_bounds;
+use quote::{format_ident, quote};
+use syn::{Generics, Ident, Path};
+
+/// Generate `encode` for the top-level enum, `Ref` or `RefMut` named `type_name`, and
+/// `decode_as` for the top-level enum if `decode` is set.
+///
+/// The `generics` are the generics of `type_name`, while `struct_generics` are the generics of the
+/// variant structs. If `leaf_variant_names` is given, then the variants are inner enums with these
+/// variants, whose `encode` methods are used rather than the codec trait.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_codec_methods(
+    type_name: &Ident,
+    generics: &Generics,
+    struct_generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+    codec: &Path,
+    decode: bool,
+    leaf_variant_names: Option<&[Ident]>,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+
+    let (encode_bounds, encode_calls) = if let Some(leaf_variant_names) = leaf_variant_names {
+        // The inner enums' `encode` methods require the codec for each of their variant structs.
+        let leaf_struct_names = struct_names
+            .iter()
+            .flat_map(|struct_name| {
+                leaf_variant_names
+                    .iter()
+                    .map(move |variant_name| format_ident!("{}{}", struct_name, variant_name))
+            })
+            .collect::<Vec<_>>();
+        (
+            with_variant_bounds(
+                None,
+                &leaf_struct_names,
+                &struct_ty_generics,
+                quote! { #codec },
+            ),
+            variant_names
+                .iter()
+                .map(|_| quote! { inner.encode() })
+                .collect::<Vec<_>>(),
+        )
+    } else {
+        (
+            with_variant_bounds(None, struct_names, &struct_ty_generics, quote! { #codec }),
+            struct_names
+                .iter()
+                .map(|struct_name| {
+                    quote! { <#struct_name #struct_ty_generics as #codec>::encode(inner) }
+                })
+                .collect(),
+        )
+    };
+
+    let decode_as = decode.then(|| {
+        let kind_name = generate_kind_enum_name(type_name);
+        // All variants must have the same error type as the first.
+        let (first_struct_name, other_struct_names) = struct_names
+            .split_first()
+            .expect("there is at least one variant");
+        let error = quote! { <#first_struct_name #struct_ty_generics as #codec>::Error };
+        quote! {
+            /// Decode the variant struct of the given `kind` from `bytes`.
+            pub fn decode_as(kind: #kind_name, bytes: &[u8]) -> Result<Self, #error>
+            where
+                #first_struct_name #struct_ty_generics: #codec,
+                #(#other_struct_names #struct_ty_generics: #codec<Error = #error>,)*
+            {
+                match kind {
+                    #(
+                        #kind_name::#variant_names => {
+                            <#struct_names #struct_ty_generics as #codec>::decode(bytes)
+                                .map(Self::from)
+                        }
+                    )*
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics #type_name #ty_generics #where_clause {
+            /// Encode the variant struct.
+            pub fn encode(&self) -> ::std::vec::Vec<u8>
+            #encode_bounds
+            {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #encode_calls,
+                    )*
+                }
+            }
+
+            #decode_as
+        }
+    }
+}
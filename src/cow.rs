@@ -0,0 +1,144 @@
+//! Generate the copy-on-write `Cow` type, with the `cow` option.
+use crate::utils::{unboxed, with_variant_bounds, Boxing};
+use proc_macro2::Span;
+use quote::quote;
+use syn::{GenericParam, Generics, Ident, Lifetime, LifetimeParam, Visibility};
+
+/// Generate the `Cow` type named `cow_ty_name`, its conversions and its `methods`.
+///
+/// The `ref_generics` are the generics of the `Ref` type, which the `Cow` type shares.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_cow_type(
+    type_name: &Ident,
+    cow_ty_name: &Ident,
+    ref_ty_name: &Ident,
+    ref_mut_ty_name: &Ident,
+    visibility: &Visibility,
+    generics: &Generics,
+    ref_generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+    boxed: &[Boxing],
+    methods: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let (cow_impl_generics, cow_ty_generics, _) = ref_generics.split_for_impl();
+    let cow_lifetime = ref_generics
+        .lifetimes()
+        .next()
+        .map(|param| &param.lifetime)
+        .expect("ref generics begin with a lifetime");
+
+    // `Cow` requires `ToOwned`, which is implemented by every `Clone` type.
+    let where_clause = with_variant_bounds(
+        generics.where_clause.as_ref(),
+        struct_names,
+        &ty_generics,
+        quote! { Clone },
+    );
+
+    // `RefMut` borrowed from the `Cow` for an elided lifetime.
+    let mut ref_mut_generics = generics.clone();
+    ref_mut_generics.params.insert(
+        0,
+        GenericParam::Lifetime(LifetimeParam::new(Lifetime::new("'_", Span::call_site()))),
+    );
+    let (_, ref_mut_ty_generics, _) = ref_mut_generics.split_for_impl();
+
+    let owned_inners = variant_names
+        .iter()
+        .zip(boxed)
+        .map(|(variant_name, boxing)| unboxed(quote! { inner }, *boxing, type_name, variant_name));
+
+    let cow_doc = format!(
+        "A copy-on-write version of [`{type_name}`], holding either a reference to a variant \
+        struct or an owned variant struct."
+    );
+
+    quote! {
+        #[doc = #cow_doc]
+        #visibility enum #cow_ty_name #ref_generics #where_clause {
+            #(
+                #variant_names(::std::borrow::Cow<#cow_lifetime, #struct_names #ty_generics>),
+            )*
+        }
+
+        impl #cow_impl_generics #cow_ty_name #cow_ty_generics #where_clause {
+            /// Get a mutable reference to the variant struct, cloning it if it is borrowed.
+            pub fn to_mut(&mut self) -> #ref_mut_ty_name #ref_mut_ty_generics {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => {
+                            #ref_mut_ty_name::#variant_names(inner.to_mut())
+                        }
+                    )*
+                }
+            }
+
+            /// Convert to the top-level enum, cloning the variant struct if it is borrowed.
+            pub fn into_owned(self) -> #type_name #ty_generics {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #type_name::from(inner.into_owned()),
+                    )*
+                }
+            }
+
+            #methods
+        }
+
+        impl #cow_impl_generics Clone for #cow_ty_name #cow_ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => Self::#variant_names(inner.clone()),
+                    )*
+                }
+            }
+        }
+
+        impl #cow_impl_generics From<#type_name #ty_generics> for #cow_ty_name #cow_ty_generics
+        #where_clause
+        {
+            fn from(owned: #type_name #ty_generics) -> Self {
+                match owned {
+                    #(
+                        #type_name::#variant_names(inner) => {
+                            Self::#variant_names(::std::borrow::Cow::Owned(#owned_inners))
+                        }
+                    )*
+                }
+            }
+        }
+
+        impl #cow_impl_generics From<#ref_ty_name #cow_ty_generics> for #cow_ty_name #cow_ty_generics
+        #where_clause
+        {
+            fn from(borrowed: #ref_ty_name #cow_ty_generics) -> Self {
+                match borrowed {
+                    #(
+                        #ref_ty_name::#variant_names(inner) => {
+                            Self::#variant_names(::std::borrow::Cow::Borrowed(inner))
+                        }
+                    )*
+                }
+            }
+        }
+
+        impl #cow_impl_generics From<&#cow_lifetime #type_name #ty_generics>
+            for #cow_ty_name #cow_ty_generics #where_clause
+        {
+            fn from(borrowed: &#cow_lifetime #type_name #ty_generics) -> Self {
+                Self::from(borrowed.to_ref())
+            }
+        }
+
+        impl #cow_impl_generics From<#cow_ty_name #cow_ty_generics> for #type_name #ty_generics
+        #where_clause
+        {
+            fn from(cow: #cow_ty_name #cow_ty_generics) -> Self {
+                cow.into_owned()
+            }
+        }
+    }
+}
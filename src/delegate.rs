@@ -0,0 +1,454 @@
+//! Implement traits for a superstruct by delegating each method to the variant structs.
+//!
+//! Like `superstruct_impl`, the `delegate` attribute doesn't know the variants of the superstruct,
+//! so it forwards the impl block to the superstruct's helper macro, which passes everything on to
+//! `__superstruct_delegate_expand`. If the impl block doesn't declare any methods, then it is
+//! first forwarded to the helper macro generated for the trait by `delegatable`, which adds the
+//! trait's definition so that its items can be delegated.
+use crate::naming::generate_delegatable_macro_name;
+use crate::superstruct_impl::ImplConfig;
+use crate::utils::{
+    contains_bare_self_type, unboxed, unboxed_ref, with_last_segment, without_arguments,
+};
+use darling::{export::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    parse_quote, Error, FnArg, GenericParam, Ident, ImplItem, ImplItemConst, ImplItemFn,
+    ImplItemType, ItemImpl, ItemTrait, Path, PathArguments, ReturnType, Signature, TraitItem, Type,
+    Visibility,
+};
+
+/// The type which a trait is being implemented for.
+#[derive(Clone, Copy, PartialEq)]
+enum DelegateFor {
+    Enum,
+    Ref,
+    RefMut,
+}
+
+/// The way that a method takes `self`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Receiver {
+    Value,
+    Ref,
+    RefMut,
+}
+
+/// The input of `__superstruct_delegate_expand`: `[config] [type_name] [trait] impl ..`.
+///
+/// The trait's definition is empty unless the trait's methods weren't declared in the impl block.
+pub struct DelegateExpandInput {
+    config: TokenStream,
+    type_name: Ident,
+    item_trait: Option<ItemTrait>,
+    item_impl: ItemImpl,
+}
+
+impl Parse for DelegateExpandInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let config;
+        bracketed!(config in input);
+        let type_name;
+        bracketed!(type_name in input);
+        let item_trait;
+        bracketed!(item_trait in input);
+        Ok(Self {
+            config: config.parse()?,
+            type_name: type_name.parse()?,
+            item_trait: if item_trait.is_empty() {
+                None
+            } else {
+                Some(item_trait.parse()?)
+            },
+            item_impl: input.parse()?,
+        })
+    }
+}
+
+/// Forward an impl block marked with `delegate(type_name)` to the helper macro of the superstruct.
+///
+/// If the impl block doesn't declare any methods, it is forwarded via the helper macro of the trait.
+pub fn delegate(type_name: &Ident, item_impl: ItemImpl) -> syn::Result<TokenStream> {
+    let Some((_, trait_path, _)) = &item_impl.trait_ else {
+        return Err(Error::new_spanned(
+            &item_impl.self_ty,
+            "`delegate` must be used on a trait impl",
+        ));
+    };
+    let helper_macro_path = without_arguments(self_type_path(type_name, &item_impl)?);
+
+    let declares_methods = item_impl
+        .items
+        .iter()
+        .any(|impl_item| matches!(parse_declaration(impl_item), Some(TraitItem::Fn(_))));
+    if declares_methods {
+        return Ok(quote! {
+            #helper_macro_path! { delegate [#type_name] [] #item_impl }
+        });
+    }
+    let trait_macro_path = without_arguments(trait_path);
+    Ok(quote! {
+        #trait_macro_path! { [#helper_macro_path] [#type_name] #item_impl }
+    })
+}
+
+/// Generate the helper macro for a trait marked with `delegatable`, which passes its definition on
+/// to the helper macro of a superstruct.
+pub fn delegatable(item_trait: ItemTrait) -> TokenStream {
+    let trait_name = &item_trait.ident;
+    let macro_name = generate_delegatable_macro_name(trait_name);
+    // Only the signatures are needed, so the bodies of default methods are left out.
+    let mut signatures = item_trait.clone();
+    for trait_item in &mut signatures.items {
+        if let TraitItem::Fn(method) = trait_item {
+            if method.default.is_some() {
+                method.default = Some(parse_quote!({}));
+            }
+        }
+    }
+    quote! {
+        #item_trait
+
+        macro_rules! #macro_name {
+            ([$($helper:tt)*] [$($type_name:tt)*] $($item:tt)*) => {
+                $($helper)*! { delegate [$($type_name)*] [#signatures] $($item)* }
+            };
+        }
+        #[allow(unused_imports)]
+        pub(crate) use #macro_name as #trait_name;
+    }
+}
+
+/// Fill in the items of a trait impl by delegating to the variants.
+///
+/// Every method, associated type and associated constant declared without a definition is filled
+/// in, along with every item of the trait which is missing from the impl block if its definition
+/// is known.
+pub fn delegate_expand(input: DelegateExpandInput) -> syn::Result<TokenStream> {
+    let DelegateExpandInput {
+        config,
+        type_name,
+        item_trait,
+        mut item_impl,
+    } = input;
+    let config = ImplConfig::from_list(&NestedMeta::parse_meta_list(config)?)?;
+    let ref_type_name = format_ident!("{}Ref", type_name);
+    let ref_mut_type_name = format_ident!("{}RefMut", type_name);
+
+    let self_path = self_type_path(&type_name, &item_impl)?.clone();
+    let self_type_name = &self_path.segments.last().unwrap().ident;
+    let delegate_for = if *self_type_name == type_name {
+        DelegateFor::Enum
+    } else if *self_type_name == ref_type_name {
+        DelegateFor::Ref
+    } else if *self_type_name == ref_mut_type_name {
+        DelegateFor::RefMut
+    } else {
+        return Err(Error::new_spanned(
+            &item_impl.self_ty,
+            format!(
+                "`delegate({type_name})` must be used on an impl for `{type_name}`, \
+                `{ref_type_name}` or `{ref_mut_type_name}`"
+            ),
+        ));
+    };
+    let trait_path = item_impl.trait_.as_ref().unwrap().1.clone();
+
+    // Associated types and constants are taken from the first variant, without the lifetime of
+    // the `Ref` or `RefMut` type.
+    let first_variant_path = {
+        let mut path = with_last_segment(
+            &self_path,
+            format_ident!("{}{}", type_name, config.variants.idents[0]),
+        );
+        let segment = path.segments.last_mut().unwrap();
+        segment.arguments = self_path.segments.last().unwrap().arguments.clone();
+        if delegate_for != DelegateFor::Enum {
+            if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                args.args = args.args.iter().skip(1).cloned().collect();
+                if args.args.is_empty() {
+                    segment.arguments = PathArguments::None;
+                }
+            }
+        }
+        path
+    };
+
+    // The items to fill in, and whether they may be left out because the trait provides them.
+    let mut declarations = vec![];
+    let mut impl_items = vec![];
+    for impl_item in std::mem::take(&mut item_impl.items) {
+        match parse_declaration(&impl_item) {
+            Some(trait_item) => declarations.push((trait_item, false)),
+            None => impl_items.push(impl_item),
+        }
+    }
+    if let Some(item_trait) = item_trait {
+        if !item_trait.generics.params.is_empty() {
+            return Err(Error::new_spanned(
+                &trait_path,
+                format!(
+                    "can't delegate `{}` without declaring its methods, as it has generic \
+                    parameters",
+                    item_trait.ident
+                ),
+            ));
+        }
+        let defined = impl_items
+            .iter()
+            .filter_map(|impl_item| match impl_item {
+                ImplItem::Fn(method) => Some(&method.sig.ident),
+                ImplItem::Type(ty) => Some(&ty.ident),
+                ImplItem::Const(constant) => Some(&constant.ident),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        for trait_item in item_trait.items {
+            let (ident, has_default) = match &trait_item {
+                TraitItem::Fn(method) => (&method.sig.ident, method.default.is_some()),
+                TraitItem::Type(ty) => (&ty.ident, ty.default.is_some()),
+                TraitItem::Const(constant) => (&constant.ident, constant.default.is_some()),
+                _ => continue,
+            };
+            if !defined.contains(ident) {
+                declarations.push((trait_item, has_default));
+            }
+        }
+    }
+
+    for (trait_item, has_default) in declarations {
+        let impl_item = match trait_item {
+            TraitItem::Fn(method) => {
+                match delegate_method(method.sig, delegate_for, &trait_path, &config) {
+                    Ok((sig, block)) => ImplItem::Fn(ImplItemFn {
+                        attrs: method.attrs,
+                        vis: Visibility::Inherited,
+                        defaultness: None,
+                        sig,
+                        block,
+                    }),
+                    // Methods which can't be delegated use the trait's default, if it has one.
+                    Err(_) if has_default => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            // Associated types and constants with defaults are left to the trait.
+            TraitItem::Type(_) | TraitItem::Const(_) if has_default => continue,
+            TraitItem::Type(ty) => {
+                let ident = &ty.ident;
+                let (_, ty_generics, _) = ty.generics.split_for_impl();
+                ImplItem::Type(ImplItemType {
+                    attrs: ty.attrs,
+                    vis: Visibility::Inherited,
+                    defaultness: None,
+                    type_token: ty.type_token,
+                    ident: ty.ident.clone(),
+                    generics: ty.generics.clone(),
+                    eq_token: Default::default(),
+                    ty: parse_quote!(<#first_variant_path as #trait_path>::#ident #ty_generics),
+                    semi_token: ty.semi_token,
+                })
+            }
+            TraitItem::Const(constant) => {
+                let ident = &constant.ident;
+                ImplItem::Const(ImplItemConst {
+                    attrs: constant.attrs,
+                    vis: Visibility::Inherited,
+                    defaultness: None,
+                    const_token: constant.const_token,
+                    ident: constant.ident.clone(),
+                    generics: constant.generics,
+                    colon_token: constant.colon_token,
+                    ty: constant.ty,
+                    eq_token: Default::default(),
+                    expr: parse_quote!(<#first_variant_path as #trait_path>::#ident),
+                    semi_token: constant.semi_token,
+                })
+            }
+            _ => continue,
+        };
+        impl_items.push(impl_item);
+    }
+    item_impl.items = impl_items;
+
+    Ok(quote! { #item_impl })
+}
+
+/// Generate the signature and body of a method which calls the same method of the variant.
+fn delegate_method(
+    mut sig: Signature,
+    delegate_for: DelegateFor,
+    trait_path: &Path,
+    config: &ImplConfig,
+) -> syn::Result<(Signature, syn::Block)> {
+    let fn_name = sig.ident.clone();
+    let error = |reason: &str| {
+        Err(Error::new(
+            fn_name.span(),
+            format!("can't delegate `{fn_name}`, {reason}"),
+        ))
+    };
+
+    let Some(receiver) = get_receiver(&sig) else {
+        return error("which doesn't take `self`, `&self` or `&mut self`");
+    };
+    if sig.inputs.iter().any(
+        |input| matches!(input, FnArg::Typed(pat_type) if contains_bare_self_type(&pat_type.ty)),
+    ) {
+        return error("which has an argument whose type uses `Self`");
+    }
+    // Methods returning `Self` return a variant struct, which is converted to the top-level enum.
+    let returns_self = match &sig.output {
+        ReturnType::Type(_, ty) if **ty == parse_quote!(Self) => true,
+        ReturnType::Type(_, ty) if contains_bare_self_type(ty) => {
+            return error("whose return type uses `Self` other than as `Self`");
+        }
+        _ => false,
+    };
+    if returns_self && delegate_for != DelegateFor::Enum {
+        return error("which returns `Self`, for a `Ref` or `RefMut` type");
+    }
+    if delegate_for == DelegateFor::Ref && receiver == Receiver::RefMut {
+        return error("which takes `&mut self`, for a `Ref` type");
+    }
+
+    // Use the `self` token from the method, as the generated code is within a macro expansion.
+    let self_token = sig.receiver().unwrap().self_token;
+    if let Some(FnArg::Receiver(receiver)) = sig
+        .inputs
+        .first_mut()
+        .filter(|_| receiver == Receiver::Value)
+    {
+        // A `mut self` receiver only matters to the body of the variant's method.
+        receiver.mutability = None;
+    }
+    let args = rename_args(&mut sig);
+    let turbofish = make_turbofish(&sig);
+
+    let arms = config.variants.idents.iter().map(|variant_name| {
+        // Get the variant struct (or a reference to it) from the `inner` binding.
+        let boxing = config.boxing(variant_name);
+        let inner = match (delegate_for, receiver) {
+            (DelegateFor::Enum, Receiver::Value) => {
+                unboxed(quote! { inner }, boxing, quote! { Self }, variant_name)
+            }
+            (DelegateFor::Enum, Receiver::Ref) => unboxed_ref(
+                quote! { inner },
+                boxing,
+                quote! { Self },
+                variant_name,
+                false,
+            ),
+            (DelegateFor::Enum, Receiver::RefMut) => unboxed_ref(
+                quote! { inner },
+                boxing,
+                quote! { Self },
+                variant_name,
+                true,
+            ),
+            (_, Receiver::Value) => quote! { inner },
+            (DelegateFor::Ref, _) => quote! { *inner },
+            (DelegateFor::RefMut, Receiver::Ref) => quote! { &**inner },
+            (DelegateFor::RefMut, Receiver::RefMut) => quote! { &mut **inner },
+        };
+        let call = quote! { <_ as #trait_path>::#fn_name #turbofish(#inner, #(#args),*) };
+        if returns_self {
+            quote! { Self::#variant_name(inner) => Self::from(#call), }
+        } else {
+            quote! { Self::#variant_name(inner) => #call, }
+        }
+    });
+    let block = parse_quote!({
+        match #self_token {
+            #(#arms)*
+        }
+    });
+    Ok((sig, block))
+}
+
+/// Get the path of the type that an impl block for the superstruct `type_name` is for.
+fn self_type_path<'a>(type_name: &Ident, item_impl: &'a ItemImpl) -> syn::Result<&'a Path> {
+    match &*item_impl.self_ty {
+        Type::Path(type_path) if type_path.qself.is_none() => Ok(&type_path.path),
+        self_ty => Err(Error::new_spanned(
+            self_ty,
+            format!("`delegate({type_name})` must be used on an impl for `{type_name}`"),
+        )),
+    }
+}
+
+/// Parse an item of an impl block which is declared without a definition, like a trait item.
+///
+/// Such items can't be parsed as impl items, so they are left as verbatim tokens by `syn`.
+fn parse_declaration(impl_item: &ImplItem) -> Option<TraitItem> {
+    let ImplItem::Verbatim(tokens) = impl_item else {
+        return None;
+    };
+    match syn::parse2::<TraitItem>(tokens.clone()).ok()? {
+        TraitItem::Fn(method) if method.default.is_none() => Some(TraitItem::Fn(method)),
+        TraitItem::Type(ty) if ty.default.is_none() => Some(TraitItem::Type(ty)),
+        TraitItem::Const(constant) if constant.default.is_none() => {
+            Some(TraitItem::Const(constant))
+        }
+        _ => None,
+    }
+}
+
+/// Get the way that a method takes `self`, unless it doesn't or `self` has an explicit type.
+pub fn get_receiver(sig: &Signature) -> Option<Receiver> {
+    let receiver = sig.receiver()?;
+    if receiver.colon_token.is_some() {
+        return None;
+    }
+    match &receiver.reference {
+        None => Some(Receiver::Value),
+        Some(_) if receiver.mutability.is_some() => Some(Receiver::RefMut),
+        Some(_) => Some(Receiver::Ref),
+    }
+}
+
+/// Name each argument after `self` as `arg0`, `arg1`, etc, and return the names.
+pub fn rename_args(sig: &mut Signature) -> Vec<Ident> {
+    sig.inputs
+        .iter_mut()
+        .filter_map(|input| match input {
+            FnArg::Typed(pat_type) => Some(pat_type),
+            FnArg::Receiver(_) => None,
+        })
+        .enumerate()
+        .map(|(i, pat_type)| {
+            let arg = format_ident!("arg{}", i);
+            *pat_type.pat = parse_quote!(#arg);
+            arg
+        })
+        .collect()
+}
+
+/// Pass the type and const generic parameters of a method on to the variant's method.
+///
+/// Methods with `impl Trait` arguments can't be called with explicit generic arguments, so these
+/// are inferred.
+pub fn make_turbofish(sig: &Signature) -> TokenStream {
+    let has_impl_trait = sig.inputs.iter().any(|input| {
+        matches!(input, FnArg::Typed(pat_type) if matches!(*pat_type.ty, Type::ImplTrait(_)))
+    });
+    let params = sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(type_param) => Some(&type_param.ident),
+            GenericParam::Const(const_param) => Some(&const_param.ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect::<Vec<_>>();
+    if has_impl_trait || params.is_empty() {
+        quote! {}
+    } else {
+        quote! { ::<#(#params),*> }
+    }
+}
@@ -1,4 +1,5 @@
 //! Generate `From` implementations to convert variants to the top-level enum.
+use crate::utils::{with_variant_bounds, Boxing};
 use quote::quote;
 use syn::{Ident, ImplGenerics, Lifetime, TypeGenerics, WhereClause};
 
@@ -9,11 +10,18 @@ pub fn generate_from_variant_trait_impl(
     where_clause: &Option<&WhereClause>,
     variant_name: &Ident,
     struct_name: &Ident,
+    boxing: Boxing,
 ) -> proc_macro2::TokenStream {
+    let variant = match boxing {
+        Boxing::Unboxed => quote! { variant },
+        Boxing::Boxed => quote! { ::std::boxed::Box::new(variant) },
+        // Boxes the variant struct or leaves it as is, whichever the variant's type is.
+        Boxing::Auto => quote! { ::core::convert::From::from(variant) },
+    };
     quote! {
         impl #impl_generics From<#struct_name #ty_generics> for #type_name #ty_generics #where_clause {
             fn from(variant: #struct_name #ty_generics) -> Self {
-                Self::#variant_name(variant)
+                Self::#variant_name(#variant)
             }
         }
     }
@@ -56,3 +64,53 @@ pub fn generate_from_enum_trait_impl_for_ref(
         }
     }
 }
+
+/// Generate `cloned` for the `Ref` type and the `From` implementation which uses it, converting
+/// back to the top-level enum by cloning the variant struct.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_cloned_method_for_ref(
+    ty_name: &Ident,
+    ty_generics: &TypeGenerics,
+    ref_ty_name: &Ident,
+    ref_impl_generics: &ImplGenerics,
+    ref_ty_generics: &TypeGenerics,
+    where_clause: &Option<&WhereClause>,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+) -> proc_macro2::TokenStream {
+    let clone_bounds = with_variant_bounds(
+        None,
+        struct_names,
+        ty_generics,
+        quote! { ::core::clone::Clone },
+    );
+    let from_where_clause = with_variant_bounds(
+        where_clause.as_ref().copied(),
+        struct_names,
+        ty_generics,
+        quote! { ::core::clone::Clone },
+    );
+    quote! {
+        impl #ref_impl_generics #ref_ty_name #ref_ty_generics #where_clause {
+            /// Clone the variant struct to create an owned value of the top-level enum.
+            pub fn cloned(&self) -> #ty_name #ty_generics
+            #clone_bounds
+            {
+                match self {
+                    #(
+                        #ref_ty_name::#variant_names(inner)
+                            => #ty_name::from(::core::clone::Clone::clone(*inner)),
+                    )*
+                }
+            }
+        }
+
+        impl #ref_impl_generics From<#ref_ty_name #ref_ty_generics> for #ty_name #ty_generics
+        #from_where_clause
+        {
+            fn from(ref_to_variant: #ref_ty_name #ref_ty_generics) -> Self {
+                ref_to_variant.cloned()
+            }
+        }
+    }
+}
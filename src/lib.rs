@@ -3,27 +3,54 @@ use std::{
     iter::{self, FromIterator},
 };
 
+use accessor_trait::{
+    generate_accessor_trait, generate_accessor_trait_impl_for_enum, group_accessor_traits,
+    AccessorImplFor,
+};
 use attributes::{IdentList, NestedMetaList};
+use codec::generate_codec_methods;
+use cow::generate_cow_type;
 use darling::{export::NestedMeta, util::Override, FromMeta};
+use delegate::DelegateExpandInput;
 use from::{
-    generate_from_enum_trait_impl_for_ref, generate_from_variant_trait_impl,
-    generate_from_variant_trait_impl_for_ref,
+    generate_cloned_method_for_ref, generate_from_enum_trait_impl_for_ref,
+    generate_from_variant_trait_impl, generate_from_variant_trait_impl_for_ref,
 };
 use itertools::{izip, Itertools};
-use macros::generate_all_map_macros;
+use macros::{generate_all_leaf_map_macros, generate_all_map_macros};
+use naming::{generate_box_selector_trait_name, generate_unbox_method_name};
 use proc_macro::TokenStream;
-use proc_macro2::{Span, TokenStream as TokenStream2};
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
 use quote::{format_ident, quote, ToTokens};
+use ref_traits::generate_ref_trait_impls;
+use serde::{
+    generate_deserialize_as, generate_mirror_deserialize_impl, generate_mirror_serialize_impl,
+    generate_serialize_impl, generate_untagged_deserialize, SerdeAttributes, SerializeFor,
+};
+use superstruct_impl::{generate_impl_helper_macro, ImplExpandInput};
 use syn::{
-    parse_macro_input, Attribute, Expr, Field, GenericParam, Ident, ItemStruct, Lifetime,
-    LifetimeParam, Type, TypeGenerics, TypeParamBound,
+    parse_macro_input, parse_quote, Attribute, Expr, Field, GenericParam, Generics, Ident,
+    ItemImpl, ItemStruct, ItemTrait, Lifetime, LifetimeParam, Meta, Path, Type, TypeGenerics,
+    TypeParamBound, Visibility,
 };
+use utils::{contains_self_type, replace_self_type, unused_generic_params, Boxing};
+use variant_trait::{generate_kind_enum, generate_variant_kind_method, generate_variant_trait};
+use visitor::{generate_enum_accept_methods, generate_ref_accept_methods, generate_visitor_traits};
 
+mod accessor_trait;
 mod attributes;
+mod codec;
+mod cow;
+mod delegate;
 mod from;
 mod macros;
 mod naming;
+mod ref_traits;
+mod serde;
+mod superstruct_impl;
 mod utils;
+mod variant_trait;
+mod visitor;
 
 /// Top-level configuration via the `superstruct` attribute.
 #[derive(Debug, FromMeta)]
@@ -51,6 +78,9 @@ struct StructOpts {
     /// List of attributes to apply to the generated RefMut type.
     #[darling(default)]
     ref_mut_attributes: Option<NestedMetaList>,
+    /// List of attributes to apply to the `PhantomData` field of variant structs.
+    #[darling(default)]
+    phantom_attributes: Option<NestedMetaList>,
     /// Error type and expression to use for casting methods.
     #[darling(default)]
     cast_error: ErrorOpts,
@@ -63,6 +93,54 @@ struct StructOpts {
     /// Turn off the generation of the map macros.
     #[darling(default)]
     no_map_macros: bool,
+    /// Generate the `FooKind` enum and `variant_kind` methods.
+    #[darling(default)]
+    variant_kind: bool,
+    /// Generate the `FooVariant` trait implemented by the variant structs, and `try_get` methods.
+    #[darling(default)]
+    variant_trait: bool,
+    /// Generate the visitor traits and `accept` methods.
+    #[darling(default)]
+    visitors: bool,
+    /// Generate serde implementations for the top-level enum and `Ref` (requires the `serde`
+    /// feature).
+    #[darling(default)]
+    serde: bool,
+    /// Implement `Deserialize` for the top-level enum by trying the variants from most to least
+    /// specific (requires the `serde` feature).
+    #[darling(default)]
+    untagged_deserialize: Option<UntaggedDeserializeOpts>,
+    /// Implement `Serialize` and `Deserialize` for the top-level enum with a tag naming the
+    /// variant (requires the `serde` feature).
+    #[darling(default)]
+    serde_tag: Option<SerdeTagOpts>,
+    /// Implement `Serialize` for `Ref` and `RefMut` to match the top-level enum's implementation,
+    /// using its `serde` attributes (requires the `serde` feature).
+    #[darling(default)]
+    ref_serialize: bool,
+    /// Path to a codec trait implemented by each variant struct, used to generate `encode` and
+    /// `decode_as` methods. The trait must have `type Error`,
+    /// `fn decode(bytes: &[u8]) -> Result<Self, Self::Error>` and `fn encode(&self) -> Vec<u8>`.
+    #[darling(default)]
+    variant_codec: Option<String>,
+    /// Generate `cloned` for `Ref` and `From<Ref>` for the top-level enum, which require the
+    /// variant structs to be `Clone`.
+    #[darling(default)]
+    ref_cloned: bool,
+    /// Generate a copy-on-write `Cow` type, which requires the variant structs to be `Clone`.
+    #[darling(default)]
+    cow: bool,
+    /// Traits to implement for `Ref` by comparing, hashing or formatting the variant struct.
+    #[darling(default)]
+    ref_traits: Option<IdentList>,
+    /// List of variants to store in a `Box` in the top-level enum, or all variants if no list is
+    /// given.
+    #[darling(default)]
+    boxed: Option<Override<IdentList>>,
+    /// Box the variants whose variant structs are larger than this many bytes, in addition to
+    /// those listed in `boxed`. Can't be used with generic structs.
+    #[darling(default)]
+    box_variants_larger_than: Option<usize>,
     /// List of other superstruct types to generate (owned) mappings into.
     #[darling(default)]
     map_into: Option<IdentList>,
@@ -72,6 +150,74 @@ struct StructOpts {
     /// List of other superstruct types to generate mappings into from RefMut.
     #[darling(default)]
     map_ref_mut_into: Option<IdentList>,
+    /// Visibility of the map macros, which are exported from the crate root by default.
+    #[darling(default)]
+    map_macro_vis: Option<MapMacroVis>,
+    /// Name added to the hidden names of map macros exported with `map_macro_vis = "pub"`, to tell
+    /// apart types with the same name.
+    #[darling(default)]
+    map_macro_disambiguator: Option<Ident>,
+    /// Path to the module containing this type (and any `map_into` types) for use by map macros.
+    #[darling(default)]
+    map_macro_path: Option<String>,
+    /// What `Self` refers to in the types of fields.
+    #[darling(default)]
+    self_means: SelfMeans,
+    /// Path to the `superstruct` crate, used by the helper macro for `superstruct_impl`.
+    #[darling(default)]
+    crate_path: Option<Path>,
+}
+
+impl StructOpts {
+    /// Whether to generate the `FooKind` enum, which is also used by the variant trait and by the
+    /// methods taking a kind.
+    fn has_variant_kind(&self) -> bool {
+        self.variant_kind || self.has_variant_trait() || self.serde || self.variant_codec.is_some()
+    }
+
+    /// Whether to generate the `FooVariant` trait, which is also used by the visitor traits.
+    fn has_variant_trait(&self) -> bool {
+        self.variant_trait || self.visitors
+    }
+
+    /// Whether the variant struct for `variant_name` is stored in a `Box`.
+    fn boxing(&self, variant_name: &Ident) -> Boxing {
+        let is_boxed = match &self.boxed {
+            None => false,
+            Some(Override::Inherit) => true,
+            Some(Override::Explicit(list)) => list.idents.contains(variant_name),
+        };
+        if is_boxed {
+            Boxing::Boxed
+        } else if self.box_variants_larger_than.is_some() {
+            Boxing::Auto
+        } else {
+            Boxing::Unboxed
+        }
+    }
+}
+
+/// Visibility of the generated map macros.
+#[derive(Debug, FromMeta)]
+#[darling(rename_all = "snake_case")]
+enum MapMacroVis {
+    /// Usable within the defining module and its children, e.g. `self::map_foo!`.
+    Module,
+    /// Usable anywhere in the crate, e.g. `crate::types::map_foo!`.
+    Crate,
+    /// Usable from other crates, e.g. `my_crate::types::map_foo!`.
+    Pub,
+}
+
+/// The type that `Self` is rewritten to within the types of fields.
+#[derive(Debug, Default, FromMeta)]
+#[darling(rename_all = "snake_case")]
+enum SelfMeans {
+    /// The top-level enum.
+    #[default]
+    Enum,
+    /// The variant struct containing the field.
+    Variant,
 }
 
 /// Field-level configuration.
@@ -91,6 +237,15 @@ struct FieldOpts {
     #[darling(default)]
     partial_getter: Option<GetterOpts>,
     no_getter: darling::util::Flag,
+    /// List of attributes to apply to this field in a selection of named (meta) variants.
+    #[darling(default)]
+    field_attributes: Option<HashMap<Ident, NestedMetaList>>,
+    /// Map from (meta) variant name to the index of this field in that variant's struct.
+    #[darling(default)]
+    position: Option<HashMap<Ident, usize>>,
+    /// Name of a trait with accessor methods for this field, implemented by the types containing it.
+    #[darling(default)]
+    accessor_trait: Option<Ident>,
 }
 
 /// Getter configuration for a specific field
@@ -102,6 +257,93 @@ struct GetterOpts {
     no_mut: bool,
     #[darling(default)]
     rename: Option<Ident>,
+    #[darling(default, rename = "dyn")]
+    dyn_trait: Option<String>,
+}
+
+/// Configuration for the `Deserialize` implementation generated by `untagged_deserialize`.
+#[derive(Debug, FromMeta)]
+struct UntaggedDeserializeOpts {
+    /// Reject variants which don't have every field of the input.
+    #[darling(default)]
+    strict: bool,
+    /// Variants to try first, in order, before the rest in order of field count.
+    #[darling(default)]
+    order: Option<IdentList>,
+    /// Self-describing type to buffer the input in, like `serde_json::Value`.
+    buffer: String,
+}
+
+/// Configuration for the serde implementations generated by `serde_tag`.
+#[derive(Debug, FromMeta)]
+struct SerdeTagOpts {
+    /// Name of the tag field, which is placed alongside the fields of the variant struct.
+    #[darling(default)]
+    field: Option<String>,
+    /// Place the tag and the variant struct in separate fields, named by `tag` and `content`.
+    #[darling(default)]
+    adjacent: bool,
+    #[darling(default)]
+    tag: Option<String>,
+    #[darling(default)]
+    content: Option<String>,
+    /// Case convention of the variant names in the tag, as for `serde(rename_all)`.
+    #[darling(default)]
+    rename_all: Option<String>,
+    /// Names of specific variants in the tag.
+    #[darling(default)]
+    rename: Option<HashMap<Ident, String>>,
+}
+
+impl SerdeTagOpts {
+    /// Get the serde attributes for the tagged enum and its variants.
+    fn serde_attributes(&self, variant_names: &[Ident]) -> SerdeAttributes {
+        let mut container = if self.adjacent {
+            assert!(
+                self.field.is_none(),
+                "`serde_tag(adjacent)` uses `tag` and `content` rather than `field`"
+            );
+            let (Some(tag), Some(content)) = (&self.tag, &self.content) else {
+                panic!("`serde_tag(adjacent)` requires `tag` and `content`");
+            };
+            vec![quote! { tag = #tag }, quote! { content = #content }]
+        } else {
+            assert!(
+                self.tag.is_none() && self.content.is_none(),
+                "`tag` and `content` may only be used with `serde_tag(adjacent)`"
+            );
+            let field = self
+                .field
+                .as_ref()
+                .expect("`serde_tag` requires `field` or `adjacent`");
+            vec![quote! { tag = #field }]
+        };
+        if let Some(rename_all) = &self.rename_all {
+            container.push(quote! { rename_all = #rename_all });
+        }
+
+        let renames = self.rename.as_ref();
+        for name in renames.into_iter().flat_map(HashMap::keys) {
+            assert!(
+                variant_names.contains(name),
+                "invalid variant name in `serde_tag(rename(..))`: {name}"
+            );
+        }
+        let variants = variant_names
+            .iter()
+            .map(|variant_name| {
+                renames
+                    .and_then(|renames| renames.get(variant_name))
+                    .map(|rename| quote! { rename = #rename })
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        SerdeAttributes {
+            container,
+            variants,
+        }
+    }
 }
 
 #[derive(Debug, Default, FromMeta)]
@@ -146,6 +388,7 @@ struct FieldData {
     partial_getter_opts: GetterOpts,
     no_getter: bool,
     is_common: bool,
+    accessor_trait: Option<Ident>,
 }
 
 impl FieldData {
@@ -157,6 +400,11 @@ impl FieldData {
         self.no_getter
     }
 
+    /// Whether this field is accessed through a trait object getter (`getter(dyn = "..")`).
+    fn is_dyn_getter(&self) -> bool {
+        self.getter_opts.dyn_trait.is_some()
+    }
+
     /// Checks whether this field should be included in creating
     /// partial getters for the given type name.
     fn exists_in_meta(&self, type_name: &Ident) -> bool {
@@ -182,6 +430,66 @@ struct VariantKey {
     meta_variant: Option<Ident>,
 }
 
+/// Implement a trait for a superstruct, or its `Ref` or `RefMut`, by delegating to the variants.
+///
+/// Every method in the impl block which has no body is filled in with a `match` calling the same
+/// method of the variant struct. If no methods are declared, the trait's items are delegated,
+/// which requires the trait to be marked with `delegatable`.
+#[proc_macro_attribute]
+pub fn delegate(args: TokenStream, input: TokenStream) -> TokenStream {
+    let type_name = parse_macro_input!(args as Ident);
+    let item_impl = parse_macro_input!(input as ItemImpl);
+    delegate::delegate(&type_name, item_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Allow a trait to be implemented by `delegate` without declaring its methods.
+#[proc_macro_attribute]
+pub fn delegatable(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item_trait = parse_macro_input!(input as ItemTrait);
+    if !args.is_empty() {
+        return syn::Error::new_spanned(
+            TokenStream2::from(args),
+            "`delegatable` doesn't take arguments",
+        )
+        .into_compile_error()
+        .into();
+    }
+    delegate::delegatable(item_trait).into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __superstruct_delegate_expand(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DelegateExpandInput);
+    delegate::delegate_expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generate the methods of an impl block for a superstruct on its variant structs.
+///
+/// Each method is generated on the variants given by `only(..)` in a `#[superstruct(..)]`
+/// attribute on the method, or in the arguments of this attribute, defaulting to all variants.
+/// Methods taking `self` are also generated on the superstruct, dispatching to the variants.
+#[proc_macro_attribute]
+pub fn superstruct_impl(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item_impl = parse_macro_input!(input as ItemImpl);
+    superstruct_impl::superstruct_impl(args.into(), item_impl)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __superstruct_impl_expand(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ImplExpandInput);
+    superstruct_impl::superstruct_impl_expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_attribute]
 pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
     let attr_args = match NestedMeta::parse_meta_list(args.into()) {
@@ -195,10 +503,37 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
     // Extract the generics to use for the top-level type and all variant structs.
     let decl_generics = &item.generics;
     // Generics used for the impl block.
-    let (_, _, where_clause) = &item.generics.split_for_impl();
+    let (_, ty_generics, where_clause) = &item.generics.split_for_impl();
 
     let opts = StructOpts::from_list(&attr_args).unwrap();
 
+    assert!(
+        opts.map_macro_disambiguator.is_none()
+            || matches!(opts.map_macro_vis, Some(MapMacroVis::Pub)),
+        "`map_macro_disambiguator` requires `map_macro_vis = \"pub\"`"
+    );
+
+    if let Some(Override::Explicit(boxed)) = &opts.boxed {
+        for variant_name in &boxed.idents {
+            assert!(
+                opts.variants.idents.contains(variant_name),
+                "unknown variant in `boxed`: {variant_name}"
+            );
+        }
+    }
+
+    // The size of a generic variant struct depends on its type parameters, which can't be used in
+    // the constant which selects whether it is boxed.
+    if opts.box_variants_larger_than.is_some() && !item.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &item.generics,
+            "`box_variants_larger_than` can't be used with generic structs; list the variants to \
+            box in `boxed` instead",
+        )
+        .into_compile_error()
+        .into();
+    }
+
     let mut output_items: Vec<TokenStream> = vec![];
 
     let mk_struct_name = |variant_key: &VariantKey| {
@@ -236,6 +571,8 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
 
     // Vec of field data.
     let mut fields = vec![];
+    // Vec of field names and their explicit per-variant positions.
+    let mut field_positions = vec![];
     // Map from variant or meta variant to variant fields.
     let mut variant_fields =
         HashMap::<_, _>::from_iter(variant_combinations.clone().zip(iter::repeat(vec![])));
@@ -257,6 +594,18 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
         let mut output_field = field.clone();
         output_field.attrs = discard_superstruct_attrs(&output_field.attrs);
 
+        // Rewrite `Self` in the field's type. When `Self` means the variant it differs between
+        // variant structs, so the rewrite happens per variant and the field gets no getters.
+        let mut variant_self_field = false;
+        match opts.self_means {
+            SelfMeans::Enum => {
+                replace_self_type(&mut output_field.ty, &parse_quote!(#type_name #ty_generics));
+            }
+            SelfMeans::Variant => {
+                variant_self_field = contains_self_type(&output_field.ty);
+            }
+        }
+
         // Add the field to the `variant_fields` map for all applicable variants.
         let field_variants = field_opts.only.as_ref().map_or_else(
             || variant_names.clone(),
@@ -280,21 +629,90 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
             .iter()
             .cartesian_product(field_meta_variants.iter());
 
+        if let Some(position) = field_opts.position.clone() {
+            for variant in position.keys() {
+                assert!(
+                    variant_names.contains(variant)
+                        || meta_variant_names.contains(&Some(variant.clone())),
+                    "invalid variant name in `position`: {variant}"
+                );
+                if !field_variants.contains(variant)
+                    && !field_meta_variants.contains(&Some(variant.clone()))
+                {
+                    return syn::Error::new_spanned(
+                        variant,
+                        format!("field `{name}` has a `position` for {variant}, but isn't in it"),
+                    )
+                    .into_compile_error()
+                    .into();
+                }
+            }
+            field_positions.push((name.clone(), position));
+        }
+
+        if let Some(field_attributes) = &field_opts.field_attributes {
+            for name in field_attributes.keys() {
+                assert!(
+                    variant_names.contains(name)
+                        || meta_variant_names.contains(&Some(name.clone())),
+                    "invalid variant name in `field_attributes`: {name}"
+                );
+            }
+        }
+
         for (variant, meta_variant) in only_combinations.clone() {
+            let variant_key = VariantKey {
+                variant: variant.clone(),
+                meta_variant: meta_variant.clone(),
+            };
+            let mut variant_field =
+                add_field_attributes(&output_field, &field_opts.field_attributes, &variant_key);
+            if variant_self_field {
+                let struct_name = mk_struct_name(&variant_key);
+                replace_self_type(
+                    &mut variant_field.ty,
+                    &parse_quote!(#struct_name #ty_generics),
+                );
+            }
             variant_fields
-                .get_mut(&VariantKey {
-                    variant: variant.clone(),
-                    meta_variant: meta_variant.clone(),
-                })
+                .get_mut(&variant_key)
                 .expect("invalid variant name in `only` or `meta_only`")
-                .push(output_field.clone());
+                .push(variant_field);
         }
 
         // Check field opts
-        if field_opts.only.is_some() && field_opts.getter.is_some() {
+        let has_non_dyn_getter = field_opts
+            .getter
+            .as_ref()
+            .is_some_and(|getter| getter.dyn_trait.is_none());
+        if variant_self_field && (has_non_dyn_getter || field_opts.partial_getter.is_some()) {
+            let attr = field.attrs.iter().find(|attr| is_superstruct_attr(attr));
+            return syn::Error::new_spanned(
+                attr,
+                format!(
+                    "field `{name}` has no getters as its type mentions `Self`, which is \
+                    different in each variant; use a `dyn` getter instead"
+                ),
+            )
+            .into_compile_error()
+            .into();
+        }
+        if field_opts.only.is_some() && has_non_dyn_getter {
             panic!("can't configure `only` and `getter` on the same field");
-        } else if field_opts.meta_only.is_some() && field_opts.getter.is_some() {
+        } else if field_opts.meta_only.is_some() && has_non_dyn_getter {
             panic!("can't configure `meta_only` and `getter` on the same field");
+        } else if field_opts
+            .getter
+            .as_ref()
+            .is_some_and(|getter| getter.dyn_trait.is_some() && getter.copy)
+        {
+            panic!("can't set `copy` on a `dyn` getter");
+        } else if field_opts
+            .partial_getter
+            .as_ref()
+            .is_some_and(|getter| getter.dyn_trait.is_some())
+        {
+            panic!("`dyn` can only be set on `getter`, not `partial_getter`");
         } else if field_opts.only.is_none()
             && field_opts.meta_only.is_none()
             && field_opts.partial_getter.is_some()
@@ -308,6 +726,17 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
             panic!("can't set `flatten` and `partial_getter` on the same field");
         } else if field_opts.flatten.is_some() && field_opts.no_getter.is_present() {
             panic!("can't set `flatten` and `no_getter` on the same field")
+        } else if field_opts.flatten.is_some() && field_opts.accessor_trait.is_some() {
+            panic!("can't set `flatten` and `accessor_trait` on the same field");
+        } else if field_opts.accessor_trait.is_some()
+            && !has_non_dyn_getter
+            && field_opts.getter.is_some()
+        {
+            panic!("can't set `accessor_trait` on a field with a `dyn` getter");
+        } else if field_opts.accessor_trait.is_some() && variant_self_field {
+            panic!("can't set `accessor_trait` on a field whose type is different in each variant");
+        } else if field_opts.getter.is_some() && field_opts.partial_getter.is_some() {
+            panic!("can't set `getter` and `partial_getter` on the same field");
         } else if field_opts.getter.is_some() && field_opts.no_getter.is_present() {
             panic!("can't set `getter` and `no_getter` on the same field")
         } else if field_opts.partial_getter.is_some() && field_opts.no_getter.is_present() {
@@ -417,6 +846,7 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
                     partial_getter_opts,
                     no_getter: false,
                     is_common: false,
+                    accessor_trait: None,
                 });
 
                 // Update the variant field map
@@ -425,7 +855,11 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
                     .expect("invalid variant name");
                 *fields
                     .get_mut(variant_field_index)
-                    .expect("invalid field index") = next_variant_field;
+                    .expect("invalid field index") = add_field_attributes(
+                    &next_variant_field,
+                    &field_opts.field_attributes,
+                    &variant_key,
+                );
             }
         } else {
             fields.push(FieldData {
@@ -439,19 +873,25 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
                     .collect_vec(),
                 getter_opts,
                 partial_getter_opts,
-                no_getter: field_opts.no_getter.is_present(),
+                no_getter: field_opts.no_getter.is_present() || variant_self_field,
                 is_common,
+                accessor_trait: field_opts.accessor_trait,
             });
         }
     }
 
+    // Move fields with explicit positions to their place in each variant.
+    for (variant_key, fields) in variant_fields.iter_mut() {
+        reorder_variant_fields(fields, &field_positions, variant_key);
+    }
+
     // Generate structs for all of the variants.
     let universal_struct_attributes = opts
         .variant_attributes
         .as_ref()
         .map_or(&[][..], |attrs| &attrs.metas);
 
-    for (variant_key, struct_name) in variant_combinations.zip(struct_names.iter()) {
+    for (variant_key, struct_name) in variant_combinations.clone().zip(struct_names.iter()) {
         let fields = &variant_fields[&variant_key];
 
         let specific_struct_attributes = opts
@@ -468,6 +908,38 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
             .iter()
             .chain(specific_struct_attributes_meta.iter());
 
+        // Generic params which aren't used by this variant's fields are used by a `PhantomData`.
+        // Unless other attributes are given, it is skipped by serde if the variant derives it.
+        let default_phantom_attributes = universal_struct_attributes
+            .iter()
+            .chain(spatt.clone())
+            .find_map(serde_skip_attribute)
+            .into_iter()
+            .collect_vec();
+        let phantom_field = make_phantom_field(
+            decl_generics,
+            fields,
+            &visibility,
+            opts.phantom_attributes
+                .as_ref()
+                .map_or(&default_phantom_attributes[..], |attrs| &attrs.metas),
+        );
+        if let Some(clashing_field) = phantom_field.as_ref().and_then(|_| {
+            fields
+                .iter()
+                .find(|f| f.ident.as_ref().unwrap() == "_phantom")
+        }) {
+            return syn::Error::new_spanned(
+                &clashing_field.ident,
+                format!(
+                    "`{struct_name}` needs a `_phantom` field for its unused generic parameters, \
+                    which clashes with this field"
+                ),
+            )
+            .into_compile_error()
+            .into();
+        }
+
         let variant_code = quote! {
             #(
                 #[#universal_struct_attributes]
@@ -479,11 +951,30 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
                 #(
                     #fields,
                 )*
+                #phantom_field
             }
         };
         output_items.push(variant_code.into());
     }
 
+    // Generate accessor traits for the variant structs containing their fields.
+    let accessor_traits = group_accessor_traits(&fields);
+    for accessor_trait in &accessor_traits {
+        let trait_struct_names = variant_combinations
+            .clone()
+            .filter(|variant_key| accessor_trait.exists_in(variant_key))
+            .map(|variant_key| mk_struct_name(&variant_key))
+            .collect_vec();
+        let accessor_trait_code = generate_accessor_trait(
+            accessor_trait,
+            &visibility,
+            decl_generics,
+            &trait_struct_names,
+            &opts.partial_getter_error,
+        );
+        output_items.push(accessor_trait_code.into());
+    }
+
     // If the `no_enum` attribute is set, stop after generating variant structs.
     if opts.no_enum {
         if opts.enum_variant_attributes.is_some() {
@@ -512,6 +1003,7 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
             variant_names,
             &inner_struct_names,
             &fields,
+            Some(meta_variant),
             false,
         );
     }
@@ -535,6 +1027,7 @@ pub fn superstruct(args: TokenStream, input: TokenStream) -> TokenStream {
         variant_names,
         struct_names,
         &fields,
+        None,
         opts.meta_variants.is_some(),
     );
 
@@ -550,6 +1043,7 @@ fn generate_wrapper_enums(
     variant_names: &[Ident],
     struct_names: &[Ident],
     fields: &[FieldData],
+    meta_variant: Option<&Ident>,
     is_meta: bool,
 ) {
     let visibility = &item.vis;
@@ -562,9 +1056,97 @@ fn generate_wrapper_enums(
     // Generics used for the impl block.
     let (impl_generics, ty_generics, where_clause) = &item.generics.split_for_impl();
 
+    // Determine which variants are boxed. The variants of a meta enum are never boxed, as they are
+    // enums of (possibly boxed) variant structs.
+    let boxed = variant_names
+        .iter()
+        .map(|variant_name| {
+            if is_meta {
+                Boxing::Unboxed
+            } else {
+                opts.boxing(variant_name)
+            }
+        })
+        .collect_vec();
+
+    // The variants boxed depending on their size store `<[(); N] as Selector>::Ty<FooA>`, where
+    // `N` is 1 if `FooA` is too large and 0 otherwise, so that `Ty<FooA>` is `Box<FooA>` or
+    // `FooA`. Wrapping uses `From` and unwrapping uses the hidden methods generated below, which
+    // work either way.
+    let box_selector_trait_name = generate_box_selector_trait_name(type_name);
+    if boxed.contains(&Boxing::Auto) {
+        output_items.push(
+            quote! {
+                #[doc(hidden)]
+                #visibility trait #box_selector_trait_name {
+                    type Ty<T>;
+                    fn unbox<T>(inner: Self::Ty<T>) -> T;
+                }
+                impl #box_selector_trait_name for [(); 0] {
+                    type Ty<T> = T;
+                    fn unbox<T>(inner: T) -> T {
+                        inner
+                    }
+                }
+                impl #box_selector_trait_name for [(); 1] {
+                    type Ty<T> = ::std::boxed::Box<T>;
+                    fn unbox<T>(inner: ::std::boxed::Box<T>) -> T {
+                        *inner
+                    }
+                }
+            }
+            .into(),
+        );
+    }
+
+    let box_selector = |struct_name: &Ident| {
+        let max_size = opts.box_variants_larger_than.unwrap();
+        quote! {
+            <
+                [(); (::core::mem::size_of::<#struct_name>() > #max_size) as usize]
+                as #box_selector_trait_name
+            >
+        }
+    };
+    let variant_tys = izip!(struct_names, &boxed)
+        .map(|(struct_name, boxing)| match boxing {
+            Boxing::Unboxed => quote! { #struct_name #ty_generics },
+            Boxing::Boxed => quote! { ::std::boxed::Box<#struct_name #ty_generics> },
+            Boxing::Auto => {
+                let box_selector = box_selector(struct_name);
+                quote! { #box_selector::Ty<#struct_name> }
+            }
+        })
+        .collect_vec();
+
+    let mut unbox_methods = vec![];
+    for (variant_name, struct_name, variant_ty, _) in
+        izip!(variant_names, struct_names, &variant_tys, &boxed)
+            .filter(|(_, _, _, boxing)| **boxing == Boxing::Auto)
+    {
+        let unbox_name = generate_unbox_method_name(variant_name, None);
+        let unbox_ref_name = generate_unbox_method_name(variant_name, Some(false));
+        let unbox_mut_name = generate_unbox_method_name(variant_name, Some(true));
+        let box_selector = box_selector(struct_name);
+        unbox_methods.push(quote! {
+            #[doc(hidden)]
+            pub fn #unbox_name(inner: #variant_ty) -> #struct_name {
+                #box_selector::unbox(inner)
+            }
+            #[doc(hidden)]
+            pub fn #unbox_ref_name(inner: &#variant_ty) -> &#struct_name {
+                inner
+            }
+            #[doc(hidden)]
+            pub fn #unbox_mut_name(inner: &mut #variant_ty) -> &mut #struct_name {
+                inner
+            }
+        });
+    }
+
     // Construct the enum variants
     let mut variants = Vec::new();
-    for (variant_name, struct_name) in izip!(variant_names, struct_names) {
+    for (variant_name, variant_ty) in variant_names.iter().zip(&variant_tys) {
         let specific_enum_variant_attributes = opts
             .specific_enum_variant_attributes
             .as_ref()
@@ -577,7 +1159,7 @@ fn generate_wrapper_enums(
             #(
                 #[#specific_enum_variant_attributes]
             )*
-            #variant_name(#struct_name #ty_generics),
+            #variant_name(#variant_ty),
         };
         variants.push(variant);
     }
@@ -596,6 +1178,12 @@ fn generate_wrapper_enums(
     };
     output_items.push(enum_item.into());
 
+    // Construct the enum of variant kinds.
+    if opts.has_variant_kind() {
+        let kind_enum = generate_kind_enum(type_name, visibility, variant_names);
+        output_items.push(kind_enum.into());
+    }
+
     // Construct a top-level reference type.
     // TODO: check that variants aren't called `Ref`
     let ref_ty_name = format_ident!("{}Ref", type_name);
@@ -679,19 +1267,19 @@ fn generate_wrapper_enums(
     // Construct the main impl block.
     let getters = fields
         .iter()
-        .filter(|f| f.is_common() && !f.no_getter())
+        .filter(|f| f.is_common() && !f.no_getter() && !f.is_dyn_getter())
         .map(|field_data| make_field_getter(type_name, variant_names, field_data, None, is_meta));
 
     let mut_getters = fields
         .iter()
-        .filter(|f| f.is_common() && !f.no_getter() && !f.getter_opts.no_mut)
+        .filter(|f| f.is_common() && !f.no_getter() && !f.is_dyn_getter() && !f.getter_opts.no_mut)
         .map(|field_data| {
             make_mut_field_getter(type_name, variant_names, field_data, None, is_meta)
         });
 
     let partial_getters = fields
         .iter()
-        .filter(|f| !f.is_common() && !f.no_getter())
+        .filter(|f| !f.is_common() && !f.no_getter() && !f.is_dyn_getter())
         .filter(|f| is_meta || f.exists_in_meta(type_name))
         .cartesian_product(&[false, true])
         .flat_map(|(field_data, mutability)| {
@@ -707,6 +1295,30 @@ fn generate_wrapper_enums(
             ))
         });
 
+    let dyn_getters = group_dyn_getters(fields);
+    let num_base_variants = opts.variants.idents.len();
+    let make_dyn_getters = |type_name: &Ident, mutable: bool, lifetime: Option<&Lifetime>| {
+        dyn_getters
+            .iter()
+            .filter(|dyn_getter| !(mutable && dyn_getter.no_mut))
+            .filter_map(|dyn_getter| {
+                make_dyn_field_getter(
+                    type_name,
+                    variant_names,
+                    dyn_getter,
+                    meta_variant,
+                    num_base_variants,
+                    &opts.partial_getter_error,
+                    mutable,
+                    lifetime,
+                    is_meta,
+                )
+            })
+            .collect_vec()
+    };
+    let dyn_field_getters = make_dyn_getters(type_name, false, None);
+    let dyn_mut_field_getters = make_dyn_getters(type_name, true, None);
+
     let cast_methods = variant_names
         .iter()
         .flat_map(|variant_name| {
@@ -728,8 +1340,13 @@ fn generate_wrapper_enums(
         })
         .collect_vec();
 
+    let variant_kind_method = opts
+        .has_variant_kind()
+        .then(|| generate_variant_kind_method(type_name, variant_names));
+
     let impl_block = quote! {
         impl #impl_generics #type_name #ty_generics #where_clause {
+            #variant_kind_method
             pub fn to_ref<#ref_ty_lifetime>(&#ref_ty_lifetime self) -> #ref_ty_name #ref_ty_generics {
                 match self {
                     #(
@@ -746,6 +1363,9 @@ fn generate_wrapper_enums(
                     )*
                 }
             }
+            #(
+                #unbox_methods
+            )*
             #(
                 #cast_methods
             )*
@@ -758,6 +1378,12 @@ fn generate_wrapper_enums(
             #(
                 #partial_getters
             )*
+            #(
+                #dyn_field_getters
+            )*
+            #(
+                #dyn_mut_field_getters
+            )*
         }
     };
     output_items.push(impl_block.into());
@@ -765,7 +1391,7 @@ fn generate_wrapper_enums(
     // Construct the impl block for the *Ref type.
     let ref_getters = fields
         .iter()
-        .filter(|f| f.is_common() && !f.no_getter())
+        .filter(|f| f.is_common() && !f.no_getter() && !f.is_dyn_getter())
         .map(|field_data| {
             make_field_getter(
                 &ref_ty_name,
@@ -778,7 +1404,7 @@ fn generate_wrapper_enums(
 
     let ref_partial_getters = fields
         .iter()
-        .filter(|f| !f.is_common() && !f.no_getter())
+        .filter(|f| !f.is_common() && !f.no_getter() && !f.is_dyn_getter())
         .filter(|f| is_meta || f.exists_in_meta(type_name))
         .flat_map(|field_data| {
             let field_variants = &field_data.only_combinations;
@@ -793,8 +1419,11 @@ fn generate_wrapper_enums(
             ))
         });
 
+    let ref_dyn_getters = make_dyn_getters(&ref_ty_name, false, Some(&ref_ty_lifetime));
+
     let ref_impl_block = quote! {
         impl #ref_impl_generics #ref_ty_name #ref_ty_generics #where_clause {
+            #variant_kind_method
             #(
                 #ref_getters
             )*
@@ -802,6 +1431,10 @@ fn generate_wrapper_enums(
             #(
                 #ref_partial_getters
             )*
+
+            #(
+                #ref_dyn_getters
+            )*
         }
 
         // Reference types are just wrappers around references, so they can be copied!
@@ -815,7 +1448,7 @@ fn generate_wrapper_enums(
     // Construct the impl block for the *RefMut type.
     let ref_mut_getters = fields
         .iter()
-        .filter(|f| f.is_common() && !f.no_getter() && !f.getter_opts.no_mut)
+        .filter(|f| f.is_common() && !f.no_getter() && !f.is_dyn_getter() && !f.getter_opts.no_mut)
         .map(|field_data| {
             make_mut_field_getter(
                 &ref_mut_ty_name,
@@ -828,7 +1461,9 @@ fn generate_wrapper_enums(
 
     let ref_mut_partial_getters = fields
         .iter()
-        .filter(|f| !f.is_common() && !f.no_getter() && !f.partial_getter_opts.no_mut)
+        .filter(|f| {
+            !f.is_common() && !f.no_getter() && !f.is_dyn_getter() && !f.partial_getter_opts.no_mut
+        })
         .filter(|f| is_meta || f.exists_in_meta(type_name))
         .flat_map(|field_data| {
             let field_variants = &field_data.only_combinations;
@@ -843,8 +1478,11 @@ fn generate_wrapper_enums(
             ))
         });
 
+    let ref_mut_dyn_getters = make_dyn_getters(&ref_mut_ty_name, true, Some(&ref_mut_ty_lifetime));
+
     let ref_mut_impl_block = quote! {
         impl #ref_mut_impl_generics #ref_mut_ty_name #ref_mut_ty_generics #where_clause {
+            #variant_kind_method
             #(
                 #ref_mut_getters
             )*
@@ -852,23 +1490,110 @@ fn generate_wrapper_enums(
             #(
                 #ref_mut_partial_getters
             )*
+
+            #(
+                #ref_mut_dyn_getters
+            )*
         }
     };
     output_items.push(ref_mut_impl_block.into());
 
+    // Construct the copy-on-write type, which has the same getters as the *Ref type.
+    if opts.cow {
+        let cow_ty_name = format_ident!("{}Cow", type_name);
+        let cow_getters = fields
+            .iter()
+            .filter(|f| f.is_common() && !f.no_getter() && !f.is_dyn_getter())
+            .map(|field_data| {
+                make_field_getter(&cow_ty_name, variant_names, field_data, None, is_meta)
+            });
+        let cow_partial_getters = fields
+            .iter()
+            .filter(|f| !f.is_common() && !f.no_getter() && !f.is_dyn_getter())
+            .filter(|f| is_meta || f.exists_in_meta(type_name))
+            .map(|field_data| {
+                make_partial_getter(
+                    &cow_ty_name,
+                    field_data,
+                    &field_data.only_combinations,
+                    &opts.partial_getter_error,
+                    false,
+                    None,
+                    is_meta,
+                )
+            });
+        let cow_dyn_getters = make_dyn_getters(&cow_ty_name, false, None);
+
+        let cow_ty = generate_cow_type(
+            type_name,
+            &cow_ty_name,
+            &ref_ty_name,
+            &ref_mut_ty_name,
+            visibility,
+            decl_generics,
+            &ref_ty_decl_generics,
+            variant_names,
+            struct_names,
+            &boxed,
+            quote! {
+                #variant_kind_method
+                #(
+                    #cow_getters
+                )*
+                #(
+                    #cow_partial_getters
+                )*
+                #(
+                    #cow_dyn_getters
+                )*
+            },
+        );
+        output_items.push(cow_ty.into());
+    }
+
+    // Generate the helper macro for `superstruct_impl`.
+    let crate_path = opts
+        .crate_path
+        .clone()
+        .unwrap_or_else(|| parse_quote!(::superstruct));
+    output_items.push(
+        generate_impl_helper_macro(
+            type_name,
+            variant_names,
+            &boxed,
+            &opts.partial_getter_error,
+            is_meta,
+            &crate_path,
+        )
+        .into(),
+    );
+
     // Generate the mapping macros if enabled.
     if !opts.no_map_macros && !opts.no_enum {
-        let num_generics = decl_generics.params.len();
         generate_all_map_macros(
             type_name,
             &ref_ty_name,
             &ref_mut_ty_name,
-            num_generics,
+            decl_generics,
             struct_names,
             variant_names,
+            &boxed,
             opts,
             output_items,
         );
+        if is_meta {
+            generate_all_leaf_map_macros(
+                type_name,
+                &ref_ty_name,
+                &ref_mut_ty_name,
+                decl_generics,
+                struct_names,
+                variant_names,
+                &opts.variants.idents,
+                opts,
+                output_items,
+            );
+        }
     } else {
         assert!(
             opts.map_into.is_none(),
@@ -885,7 +1610,7 @@ fn generate_wrapper_enums(
     }
 
     // Generate trait implementations.
-    for (variant_name, struct_name) in variant_names.iter().zip_eq(struct_names) {
+    for (variant_name, struct_name, boxed) in izip!(variant_names, struct_names, &boxed) {
         let from_impl = generate_from_variant_trait_impl(
             type_name,
             impl_generics,
@@ -893,6 +1618,7 @@ fn generate_wrapper_enums(
             where_clause,
             variant_name,
             struct_name,
+            *boxed,
         );
         output_items.push(from_impl.into());
 
@@ -920,6 +1646,326 @@ fn generate_wrapper_enums(
         where_clause,
     );
     output_items.push(ref_from_top_level_impl.into());
+
+    // Convert `Ref` back to the top-level type by cloning.
+    if opts.ref_cloned {
+        let cloned_method_for_ref = generate_cloned_method_for_ref(
+            type_name,
+            ty_generics,
+            &ref_ty_name,
+            ref_impl_generics,
+            ref_ty_generics,
+            where_clause,
+            variant_names,
+            struct_names,
+        );
+        output_items.push(cloned_method_for_ref.into());
+    }
+
+    // Generate the trait for variant structs.
+    if opts.has_variant_trait() {
+        let variant_trait = generate_variant_trait(
+            type_name,
+            &ref_ty_name,
+            &ref_mut_ty_name,
+            visibility,
+            decl_generics,
+            &ref_ty_decl_generics,
+            &ref_ty_lifetime,
+            variant_names,
+            struct_names,
+            &boxed,
+        );
+        output_items.push(variant_trait.into());
+    }
+
+    // Implement the accessor traits, which are partial for fields which aren't in every variant.
+    let variant_keys = variant_names
+        .iter()
+        .map(|variant_name| VariantKey {
+            variant: variant_name.clone(),
+            meta_variant: meta_variant.cloned(),
+        })
+        .collect_vec();
+    for accessor_trait in &group_accessor_traits(fields) {
+        let impls = [
+            (type_name, decl_generics, AccessorImplFor::Owned),
+            (&ref_ty_name, &ref_ty_decl_generics, AccessorImplFor::Ref),
+            (
+                &ref_mut_ty_name,
+                &ref_mut_ty_decl_generics,
+                AccessorImplFor::RefMut,
+            ),
+        ]
+        .into_iter()
+        .map(|(impl_type_name, impl_generics, impl_for)| {
+            generate_accessor_trait_impl_for_enum(
+                accessor_trait,
+                impl_type_name,
+                impl_generics,
+                decl_generics,
+                variant_names,
+                &variant_keys,
+                is_meta,
+                &boxed,
+                &opts.partial_getter_error,
+                impl_for,
+            )
+        });
+        output_items.extend(impls.map(TokenStream::from));
+    }
+
+    // Generate the serde implementations. The top-level enum, `Ref` and `RefMut` are all
+    // serialized in the same way.
+    let enum_types = [
+        (type_name, decl_generics, SerializeFor::Enum),
+        (&ref_ty_name, &ref_ty_decl_generics, SerializeFor::Ref),
+        (
+            &ref_mut_ty_name,
+            &ref_mut_ty_decl_generics,
+            SerializeFor::RefMut,
+        ),
+    ];
+    if opts.serde {
+        if !cfg!(feature = "serde") {
+            panic!("the `serde` option requires the `serde` feature of superstruct");
+        }
+        // With `serde_tag`, the serialized form includes the tag.
+        if opts.serde_tag.is_none() {
+            for (impl_type_name, generics, _) in enum_types {
+                let serialize_impl = generate_serialize_impl(
+                    impl_type_name,
+                    generics,
+                    decl_generics,
+                    variant_names,
+                    struct_names,
+                );
+                output_items.push(serialize_impl.into());
+            }
+        }
+
+        // The variants of a meta enum can't be deserialized from their kind alone.
+        if !is_meta {
+            let deserialize_as = generate_deserialize_as(
+                type_name,
+                visibility,
+                decl_generics,
+                variant_names,
+                struct_names,
+            );
+            output_items.push(deserialize_as.into());
+        }
+    }
+
+    if let Some(serde_tag_opts) = &opts.serde_tag {
+        if !cfg!(feature = "serde") {
+            panic!("the `serde_tag` option requires the `serde` feature of superstruct");
+        }
+        assert!(!is_meta, "`serde_tag` can't be used with meta variants");
+        assert!(
+            opts.untagged_deserialize.is_none(),
+            "`serde_tag` and `untagged_deserialize` can't be used together"
+        );
+        let serde_attributes = serde_tag_opts.serde_attributes(variant_names);
+        for (impl_type_name, generics, serialize_for) in enum_types {
+            let serialize_impl = generate_mirror_serialize_impl(
+                type_name,
+                impl_type_name,
+                generics,
+                decl_generics,
+                variant_names,
+                struct_names,
+                &serde_attributes,
+                serialize_for,
+            );
+            output_items.push(serialize_impl.into());
+        }
+        let deserialize_impl = generate_mirror_deserialize_impl(
+            type_name,
+            decl_generics,
+            variant_names,
+            struct_names,
+            &serde_attributes,
+        );
+        output_items.push(deserialize_impl.into());
+    }
+
+    // Serialize `Ref` and `RefMut` in the same way as the top-level enum's own `Serialize`.
+    if opts.ref_serialize {
+        if !cfg!(feature = "serde") {
+            panic!("the `ref_serialize` option requires the `serde` feature of superstruct");
+        }
+        assert!(
+            !opts.serde && opts.serde_tag.is_none(),
+            "`ref_serialize` can't be used with `serde` or `serde_tag`, which already implement \
+            `Serialize` for `Ref` and `RefMut`"
+        );
+        let serde_attributes = enum_serde_attributes(item, opts, variant_names);
+        for (impl_type_name, generics, serialize_for) in &enum_types[1..] {
+            let serialize_impl = generate_mirror_serialize_impl(
+                type_name,
+                impl_type_name,
+                generics,
+                decl_generics,
+                variant_names,
+                struct_names,
+                &serde_attributes,
+                *serialize_for,
+            );
+            output_items.push(serialize_impl.into());
+        }
+    }
+
+    // Implement the `ref_traits` for `Ref` by delegating to the variant structs.
+    if let Some(ref_traits) = &opts.ref_traits {
+        let ref_trait_impls = generate_ref_trait_impls(
+            type_name,
+            &ref_ty_name,
+            decl_generics,
+            &ref_ty_decl_generics,
+            variant_names,
+            struct_names,
+            &ref_traits.idents,
+            &item.attrs,
+        );
+        output_items.push(ref_trait_impls.into());
+    }
+
+    // Generate the methods which use the variant codec.
+    if let Some(codec) = &opts.variant_codec {
+        let codec: syn::Path = syn::parse_str(codec).expect("variant codec path not valid");
+        for (impl_type_name, generics, serialize_for) in enum_types {
+            let codec_methods = generate_codec_methods(
+                impl_type_name,
+                generics,
+                decl_generics,
+                variant_names,
+                struct_names,
+                &codec,
+                // The variants of a meta enum can't be decoded from their kind alone.
+                serialize_for == SerializeFor::Enum && !is_meta,
+                is_meta.then_some(&opts.variants.idents[..]),
+            );
+            output_items.push(codec_methods.into());
+        }
+    }
+
+    if let Some(untagged_opts) = &opts.untagged_deserialize {
+        if !cfg!(feature = "serde") {
+            panic!("the `untagged_deserialize` option requires the `serde` feature of superstruct");
+        }
+        let order = untagged_opts
+            .order
+            .as_ref()
+            .map_or(&[][..], |order| &order.idents);
+        for name in order {
+            assert!(
+                opts.variants.idents.contains(name)
+                    || opts
+                        .meta_variants
+                        .as_ref()
+                        .is_some_and(|meta_variants| meta_variants.idents.contains(name)),
+                "invalid variant name in `untagged_deserialize(order(..))`: {name}"
+            );
+        }
+
+        // Try the variants given by `order` first, then the rest by field count, most first.
+        // The field count of a meta variant is the largest of its variant structs.
+        let field_count = |variant_name: &Ident| {
+            let count = |variant: &Ident, meta_variant: Option<&Ident>| {
+                let variant_key = VariantKey {
+                    variant: variant.clone(),
+                    meta_variant: meta_variant.cloned(),
+                };
+                fields
+                    .iter()
+                    .filter(|field_data| field_data.only_combinations.contains(&variant_key))
+                    .count()
+            };
+            if is_meta {
+                opts.variants
+                    .idents
+                    .iter()
+                    .map(|variant| count(variant, Some(variant_name)))
+                    .max()
+                    .unwrap_or(0)
+            } else {
+                count(variant_name, meta_variant)
+            }
+        };
+        let (variants_in_order, structs_in_order): (Vec<_>, Vec<_>) =
+            izip!(variant_names, struct_names)
+                .sorted_by_key(|(variant_name, _)| {
+                    (
+                        order
+                            .iter()
+                            .position(|name| name == *variant_name)
+                            .unwrap_or(order.len()),
+                        std::cmp::Reverse(field_count(variant_name)),
+                    )
+                })
+                .unzip();
+
+        // Fields which are deserialized as a map can't be checked by `strict`.
+        if untagged_opts.strict {
+            if let Some(field_data) = fields
+                .iter()
+                .find(|field_data| field_data.field.attrs.iter().any(is_serde_flatten_attr))
+            {
+                panic!(
+                    "`untagged_deserialize(strict)` can't be used with `serde(flatten)` on `{}`",
+                    field_data.name
+                );
+            }
+        }
+
+        let buffer: Type = syn::parse_str(&untagged_opts.buffer).expect("buffer type not valid");
+        let untagged_deserialize = generate_untagged_deserialize(
+            type_name,
+            decl_generics,
+            &variants_in_order,
+            &structs_in_order,
+            untagged_opts.strict,
+            &buffer,
+        );
+        output_items.push(untagged_deserialize.into());
+    }
+
+    // Generate the visitor traits and the methods which accept them.
+    if opts.visitors {
+        let visitor_traits = generate_visitor_traits(
+            type_name,
+            visibility,
+            decl_generics,
+            variant_names,
+            struct_names,
+        );
+        output_items.push(visitor_traits.into());
+
+        let enum_accept_methods =
+            generate_enum_accept_methods(type_name, decl_generics, variant_names, &boxed);
+        output_items.push(enum_accept_methods.into());
+
+        let ref_accept_methods = generate_ref_accept_methods(
+            type_name,
+            &ref_ty_name,
+            decl_generics,
+            &ref_ty_decl_generics,
+            variant_names,
+            false,
+        );
+        output_items.push(ref_accept_methods.into());
+
+        let ref_mut_accept_methods = generate_ref_accept_methods(
+            type_name,
+            &ref_mut_ty_name,
+            decl_generics,
+            &ref_mut_ty_decl_generics,
+            variant_names,
+            true,
+        );
+        output_items.push(ref_mut_accept_methods.into());
+    }
 }
 
 /// Generate a getter method for a field.
@@ -1100,6 +2146,162 @@ fn make_partial_getter(
     }
 }
 
+/// All fields sharing a trait object getter, i.e. `getter(dyn = "..")` with the same name.
+struct DynGetter<'a> {
+    fn_name: Ident,
+    dyn_ty: Type,
+    no_mut: bool,
+    cfg_attrs: Vec<Attribute>,
+    fields: Vec<&'a FieldData>,
+}
+
+/// Group the fields with `dyn` getters by getter name, in order of first appearance.
+fn group_dyn_getters(fields: &[FieldData]) -> Vec<DynGetter<'_>> {
+    let mut dyn_getters: Vec<DynGetter> = vec![];
+
+    for field_data in fields.iter().filter(|f| f.is_dyn_getter()) {
+        let getter_opts = &field_data.getter_opts;
+        let fn_name = getter_opts.rename.as_ref().unwrap_or(&field_data.name);
+        let trait_str = getter_opts.dyn_trait.as_ref().expect("dyn getter");
+        let dyn_ty = match syn::parse_str(&format!("dyn {trait_str}")).expect("dyn trait not valid")
+        {
+            // Bounds like `Trait + Send` need parentheses to be used behind a reference.
+            Type::TraitObject(obj) if obj.bounds.len() > 1 => syn::parse_quote!((#obj)),
+            ty => ty,
+        };
+        let cfg_attrs = get_cfg_attrs(&field_data.field.attrs);
+
+        if let Some(dyn_getter) = dyn_getters.iter_mut().find(|g| g.fn_name == *fn_name) {
+            assert_eq!(
+                dyn_getter.dyn_ty.to_token_stream().to_string(),
+                dyn_ty.to_token_stream().to_string(),
+                "all fields with the `dyn` getter `{fn_name}` must use the same trait"
+            );
+            let group_cfg_attrs = &dyn_getter.cfg_attrs;
+            assert_eq!(
+                quote! { #(#group_cfg_attrs)* }.to_string(),
+                quote! { #(#cfg_attrs)* }.to_string(),
+                "all fields with the `dyn` getter `{fn_name}` must have the same `cfg` attributes"
+            );
+            dyn_getter.no_mut |= getter_opts.no_mut;
+            dyn_getter.fields.push(field_data);
+        } else {
+            dyn_getters.push(DynGetter {
+                fn_name: fn_name.clone(),
+                dyn_ty,
+                no_mut: getter_opts.no_mut,
+                cfg_attrs,
+                fields: vec![field_data],
+            });
+        }
+    }
+    dyn_getters
+}
+
+/// Generate a getter returning a trait object for a group of (possibly differently typed) fields.
+///
+/// The getter is total if every variant has one of the fields, and partial otherwise.
+#[allow(clippy::too_many_arguments)]
+fn make_dyn_field_getter(
+    type_name: &Ident,
+    variant_names: &[Ident],
+    dyn_getter: &DynGetter,
+    meta_variant: Option<&Ident>,
+    num_base_variants: usize,
+    error_opts: &ErrorOpts,
+    mutable: bool,
+    lifetime: Option<&Lifetime>,
+    is_meta: bool,
+) -> Option<proc_macro2::TokenStream> {
+    let fn_name = if mutable {
+        format_ident!("{}_mut", dyn_getter.fn_name)
+    } else {
+        dyn_getter.fn_name.clone()
+    };
+    let keys = || {
+        dyn_getter
+            .fields
+            .iter()
+            .flat_map(|f| f.only_combinations.iter().map(move |key| (key, *f)))
+    };
+
+    // For each variant, the expression accessing the field and whether that access is total.
+    let arms = variant_names
+        .iter()
+        .filter_map(|variant_name| {
+            if is_meta {
+                let num_variants = keys()
+                    .filter(|(key, _)| key.meta_variant.as_ref() == Some(variant_name))
+                    .map(|(key, _)| &key.variant)
+                    .unique()
+                    .count();
+                if num_variants == 0 {
+                    None
+                } else if num_variants == num_base_variants {
+                    Some((variant_name, quote! { inner.#fn_name() }, true))
+                } else {
+                    Some((variant_name, quote! { inner.#fn_name()? }, false))
+                }
+            } else {
+                let (_, field_data) = keys().find(|(key, _)| {
+                    key.variant == *variant_name && key.meta_variant.as_ref() == meta_variant
+                })?;
+                let field_name = &field_data.name;
+                let expr = if mutable {
+                    quote! { &mut inner.#field_name }
+                } else {
+                    quote! { &inner.#field_name }
+                };
+                Some((variant_name, expr, true))
+            }
+        })
+        .collect_vec();
+
+    // The field doesn't exist in this (inner) enum at all.
+    if arms.is_empty() {
+        return None;
+    }
+
+    let self_arg = make_self_arg(mutable, lifetime);
+    let ret_ty = make_type_ref(&dyn_getter.dyn_ty, mutable, false, lifetime);
+    let is_total = arms.len() == variant_names.len() && arms.iter().all(|(_, _, total)| *total);
+    let (arm_variants, arm_exprs, _): (Vec<_>, Vec<_>, Vec<_>) = arms.into_iter().multiunzip();
+    // Pass-through `cfg` attributes as they affect the existence of these fields.
+    let cfg_attrs = &dyn_getter.cfg_attrs;
+
+    let method = if is_total {
+        quote! {
+            #(
+                #cfg_attrs
+            )*
+            pub fn #fn_name(#self_arg) -> #ret_ty {
+                match self {
+                    #(
+                        #type_name::#arm_variants(inner) => #arm_exprs,
+                    )*
+                }
+            }
+        }
+    } else {
+        let (res_ret_ty, err_expr) = error_opts.build_result_type(&ret_ty);
+        quote! {
+            #(
+                #cfg_attrs
+            )*
+            pub fn #fn_name(#self_arg) -> #res_ret_ty {
+                match self {
+                    #(
+                        #type_name::#arm_variants(inner) => Ok(#arm_exprs),
+                    )*
+                    #[allow(unreachable_patterns)]
+                    _ => Err(#err_expr),
+                }
+            }
+        }
+    };
+    Some(method)
+}
+
 /// Generate a `as_<variant_name>{_mut}` method.
 fn make_as_variant_method(
     type_name: &Ident,
@@ -1136,6 +2338,66 @@ fn make_as_variant_method(
     }
 }
 
+/// Generate a `_phantom: PhantomData<..>` field for the generic params unused by `fields`.
+///
+/// Return `None` if every type and lifetime param is used.
+fn make_phantom_field(
+    generics: &Generics,
+    fields: &[Field],
+    visibility: &Visibility,
+    phantom_attributes: &[NestedMeta],
+) -> Option<proc_macro2::TokenStream> {
+    let unused_params = unused_generic_params(generics, fields.iter().map(|f| &f.ty));
+    let phantom_tys = unused_params
+        .into_iter()
+        .map(|param| match param {
+            GenericParam::Type(type_param) => {
+                let ident = &type_param.ident;
+                quote! { #ident }
+            }
+            GenericParam::Lifetime(lifetime_param) => {
+                let lifetime = &lifetime_param.lifetime;
+                quote! { &#lifetime () }
+            }
+            GenericParam::Const(_) => unreachable!("const params are always used"),
+        })
+        .collect_vec();
+
+    let phantom_ty = match phantom_tys.as_slice() {
+        [] => return None,
+        [ty] => ty.clone(),
+        tys => quote! { (#(#tys),*) },
+    };
+    Some(quote! {
+        #[doc(hidden)]
+        #(
+            #[#phantom_attributes]
+        )*
+        #visibility _phantom: ::core::marker::PhantomData<#phantom_ty>,
+    })
+}
+
+/// If a variant attribute derives `Serialize` or `Deserialize`, return an attribute skipping a
+/// field in serde, under the same `cfg_attr` conditions as the derive.
+fn serde_skip_attribute(meta: &NestedMeta) -> Option<NestedMeta> {
+    let NestedMeta::Meta(Meta::List(list)) = meta else {
+        return None;
+    };
+    if list.path.is_ident("derive") {
+        let derives_serde = list.tokens.clone().into_iter().any(|token| {
+            matches!(token, TokenTree::Ident(ident) if ident == "Serialize" || ident == "Deserialize")
+        });
+        derives_serde.then(|| parse_quote!(serde(skip)))
+    } else if list.path.is_ident("cfg_attr") {
+        let nested = NestedMeta::parse_meta_list(list.tokens.clone()).ok()?;
+        let (predicate, attrs) = nested.split_first()?;
+        let skip = attrs.iter().find_map(serde_skip_attribute)?;
+        Some(parse_quote!(cfg_attr(#predicate, #skip)))
+    } else {
+        None
+    }
+}
+
 /// Check that there is at most one superstruct attribute, and panic otherwise.
 fn check_for_conflicting_superstruct_attrs(attrs: &[Attribute]) {
     if attrs
@@ -1160,6 +2422,72 @@ fn discard_superstruct_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
         .collect()
 }
 
+/// Copy a field for a specific variant, adding the attributes from `field_attributes` which
+/// apply to that variant or its meta variant.
+fn add_field_attributes(
+    field: &Field,
+    field_attributes: &Option<HashMap<Ident, NestedMetaList>>,
+    variant_key: &VariantKey,
+) -> Field {
+    let mut variant_field = field.clone();
+    let Some(field_attributes) = field_attributes else {
+        return variant_field;
+    };
+    let specific_attributes = iter::once(Some(&variant_key.variant))
+        .chain(iter::once(variant_key.meta_variant.as_ref()))
+        .flatten()
+        .filter_map(|name| field_attributes.get(name))
+        .flat_map(|attrs| &attrs.metas);
+    for meta in specific_attributes {
+        variant_field.attrs.push(syn::parse_quote!(#[#meta]));
+    }
+    variant_field
+}
+
+/// Reorder the fields of a single variant so that fields with a `position` for that variant (or
+/// its meta variant) end up at the given index.
+///
+/// Positioned fields are placed in ascending order of index, so that each index refers to the
+/// final layout of the variant struct.
+fn reorder_variant_fields(
+    fields: &mut Vec<Field>,
+    field_positions: &[(Ident, HashMap<Ident, usize>)],
+    variant_key: &VariantKey,
+) {
+    let mut positioned = vec![];
+    for (name, position) in field_positions {
+        let Some(&index) = position.get(&variant_key.variant).or_else(|| {
+            variant_key
+                .meta_variant
+                .as_ref()
+                .and_then(|meta_variant| position.get(meta_variant))
+        }) else {
+            continue;
+        };
+        let Some(current_index) = fields.iter().position(|f| f.ident.as_ref() == Some(name)) else {
+            continue;
+        };
+        positioned.push((index, fields.remove(current_index)));
+    }
+
+    positioned.sort_by_key(|(index, _)| *index);
+    if let Some(((index, _), _)) = positioned.iter().tuple_windows().find(|(a, b)| a.0 == b.0) {
+        panic!(
+            "multiple fields have `position` {index} in variant {}",
+            variant_key.variant
+        );
+    }
+    for (index, field) in positioned {
+        assert!(
+            index <= fields.len(),
+            "`position` of field `{}` is out of range for variant {}",
+            field.ident.as_ref().expect("named fields only"),
+            variant_key.variant
+        );
+        fields.insert(index, field);
+    }
+}
+
 /// Keep only `cfg` attributes from an array.
 fn get_cfg_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
     attrs
@@ -1169,11 +2497,115 @@ fn get_cfg_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
         .collect()
 }
 
+/// Get the `serde` attributes of the top-level enum and its variants, for `ref_serialize`.
+///
+/// Only attributes which can be applied to the mirror enum without changing their meaning are
+/// kept. Attributes which only apply to deserialization are dropped, and any others are rejected,
+/// e.g. `with` or `bound`, which refer to the types of the top-level enum.
+fn enum_serde_attributes(
+    item: &ItemStruct,
+    opts: &StructOpts,
+    variant_names: &[Ident],
+) -> SerdeAttributes {
+    const CONTAINER_ALLOWED: &[&str] = &["tag", "content", "rename", "rename_all", "untagged"];
+    const CONTAINER_DESERIALIZE_ONLY: &[&str] = &[
+        "deny_unknown_fields",
+        "default",
+        "from",
+        "try_from",
+        "expecting",
+    ];
+    const VARIANT_ALLOWED: &[&str] = &["rename", "rename_all", "skip", "skip_serializing"];
+    const VARIANT_DESERIALIZE_ONLY: &[&str] = &[
+        "borrow",
+        "alias",
+        "skip_deserializing",
+        "deserialize_with",
+        "other",
+    ];
+
+    let serde_items = |meta: &syn::Meta, allowed: &[&str], deserialize_only: &[&str]| {
+        let items = match meta {
+            syn::Meta::List(list) if list.path.is_ident("serde") => {
+                NestedMeta::parse_meta_list(list.tokens.clone()).unwrap()
+            }
+            _ => vec![],
+        };
+        items
+            .into_iter()
+            .filter(|nested_meta| {
+                let NestedMeta::Meta(meta) = nested_meta else {
+                    return true;
+                };
+                let name = meta.path().to_token_stream().to_string();
+                if deserialize_only.contains(&name.as_str()) {
+                    return false;
+                }
+                assert!(
+                    allowed.contains(&name.as_str()),
+                    "`ref_serialize` can't be used with `serde({name})`, only with: {}",
+                    allowed.join(", ")
+                );
+                true
+            })
+            .map(|nested_meta| quote! { #nested_meta })
+            .collect::<Vec<_>>()
+    };
+    let container = item
+        .attrs
+        .iter()
+        .flat_map(|attr| serde_items(&attr.meta, CONTAINER_ALLOWED, CONTAINER_DESERIALIZE_ONLY))
+        .collect();
+
+    let enum_variant_attributes = opts
+        .enum_variant_attributes
+        .as_ref()
+        .map_or(&[][..], |attrs| &attrs.metas);
+    let variants = variant_names
+        .iter()
+        .map(|variant_name| {
+            let specific_enum_variant_attributes = opts
+                .specific_enum_variant_attributes
+                .as_ref()
+                .and_then(|map| map.get(variant_name))
+                .map_or(&[][..], |attrs| &attrs.metas);
+            enum_variant_attributes
+                .iter()
+                .chain(specific_enum_variant_attributes)
+                .filter_map(|nested_meta| match nested_meta {
+                    NestedMeta::Meta(meta) => {
+                        Some(serde_items(meta, VARIANT_ALLOWED, VARIANT_DESERIALIZE_ONLY))
+                    }
+                    NestedMeta::Lit(_) => None,
+                })
+                .flatten()
+                .collect()
+        })
+        .collect();
+
+    SerdeAttributes {
+        container,
+        variants,
+    }
+}
+
 /// Predicate for determining whether an attribute is a `superstruct` attribute.
 fn is_superstruct_attr(attr: &Attribute) -> bool {
     is_attr_with_ident(attr, "superstruct")
 }
 
+/// Predicate for determining whether an attribute is `serde(flatten)`, possibly among others.
+fn is_serde_flatten_attr(attr: &Attribute) -> bool {
+    match &attr.meta {
+        Meta::List(list) if is_attr_with_ident(attr, "serde") => list
+            .tokens
+            .clone()
+            .into_iter()
+            .any(|token| matches!(token, TokenTree::Ident(ident) if ident == "flatten")),
+        _ => false,
+    }
+}
+
 /// Predicate for determining whether an attribute has the given `ident` as its path.
 fn is_attr_with_ident(attr: &Attribute, ident: &str) -> bool {
     attr.path()
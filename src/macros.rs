@@ -1,17 +1,23 @@
 use crate::attributes::IdentList;
-use crate::naming::generate_map_macro_name;
-use crate::utils::underscore_generics;
-use crate::{StructOpts, TokenStream, TokenStream2};
-use quote::quote;
-use syn::Ident;
+use crate::naming::{
+    generate_map_leaf_macro_name, generate_map_macro_name, generate_map_pair_macro_name,
+};
+use crate::utils::{inferred_generics, unboxed, Boxing};
+use crate::{MapMacroVis, StructOpts, TokenStream, TokenStream2};
+use quote::{format_ident, quote};
+use syn::{Generics, Ident};
 
 pub struct MacroFromType<'a> {
     /// The name of the superstruct type being matched on.
     pub name: &'a Ident,
-    /// The number of generic type parameters.
-    pub num_generics: usize,
+    /// The generic parameters of the superstruct type.
+    pub generics: &'a Generics,
     /// The names of the variant structs.
     pub struct_names: &'a [Ident],
+    /// Whether each variant struct is stored in a `Box`.
+    pub boxed: &'a [Boxing],
+    /// Path prefix like `crate::types::` used to refer to types from within the macro.
+    pub path_prefix: &'a TokenStream2,
 }
 
 /// Generate all the map macros for the top-level enum, Ref and RefMut.
@@ -20,60 +26,201 @@ pub(crate) fn generate_all_map_macros(
     type_name: &Ident,
     ref_type_name: &Ident,
     ref_mut_type_name: &Ident,
-    num_generics: usize,
+    generics: &Generics,
     struct_names: &[Ident],
     variant_names: &[Ident],
+    boxed: &[Boxing],
     opts: &StructOpts,
     output_items: &mut Vec<TokenStream>,
 ) {
+    let path_prefix = opts
+        .map_macro_path
+        .as_deref()
+        .map(parse_map_macro_path)
+        .unwrap_or_default();
+    // The variants of `Ref` and `RefMut` are references, which are never boxed.
+    let not_boxed = vec![Boxing::Unboxed; variant_names.len()];
+
     generate_all_map_macros_for_type(
         type_name,
-        num_generics,
+        generics,
         struct_names,
+        boxed,
+        &path_prefix,
         &opts.map_into,
+        opts,
         |from_type, to_type| generate_owned_map_macro(from_type, to_type, variant_names),
         output_items,
     );
 
     generate_all_map_macros_for_type(
         ref_type_name,
-        num_generics,
+        generics,
         struct_names,
+        &not_boxed,
+        &path_prefix,
         &opts.map_ref_into,
+        opts,
         |from_type, to_type| generate_ref_map_macro(from_type, to_type, variant_names, false),
         output_items,
     );
 
     generate_all_map_macros_for_type(
         ref_mut_type_name,
-        num_generics,
+        generics,
         struct_names,
+        &not_boxed,
+        &path_prefix,
         &opts.map_ref_mut_into,
+        opts,
         |from_type, to_type| generate_ref_map_macro(from_type, to_type, variant_names, true),
         output_items,
     );
+
+    // Generate the pairwise map macros, which only map into `Self`.
+    for (name, boxed, ref_mutability) in [
+        (type_name, boxed, None),
+        (ref_type_name, &not_boxed[..], Some(false)),
+        (ref_mut_type_name, &not_boxed[..], Some(true)),
+    ] {
+        let from_type = MacroFromType {
+            name,
+            generics,
+            struct_names,
+            boxed,
+            path_prefix: &path_prefix,
+        };
+        let (map_macro_name, rules) =
+            generate_pair_map_macro(&from_type, variant_names, ref_mutability);
+        output_items.push(define_map_macro(&map_macro_name, rules, opts).into());
+    }
 }
 
+/// Generate the leaf map macros for the top-level enum, Ref and RefMut of a meta variant type.
+///
+/// The `struct_names` are the names of the inner enums, one per meta variant.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate_all_leaf_map_macros(
+    type_name: &Ident,
+    ref_type_name: &Ident,
+    ref_mut_type_name: &Ident,
+    generics: &Generics,
+    struct_names: &[Ident],
+    meta_variant_names: &[Ident],
+    variant_names: &[Ident],
+    opts: &StructOpts,
+    output_items: &mut Vec<TokenStream>,
+) {
+    let path_prefix = opts
+        .map_macro_path
+        .as_deref()
+        .map(parse_map_macro_path)
+        .unwrap_or_default();
+    // Whether each leaf struct is boxed within its inner enum, for the top-level enum.
+    let boxed = variant_names
+        .iter()
+        .map(|variant_name| opts.boxing(variant_name))
+        .collect::<Vec<_>>();
+    let not_boxed = vec![Boxing::Unboxed; variant_names.len()];
+
+    for (name, boxed, ref_mutability) in [
+        (type_name, &boxed, None),
+        (ref_type_name, &not_boxed, Some(false)),
+        (ref_mut_type_name, &not_boxed, Some(true)),
+    ] {
+        let from_type = MacroFromType {
+            name,
+            generics,
+            struct_names,
+            boxed,
+            path_prefix: &path_prefix,
+        };
+        let (map_macro_name, rules) = generate_leaf_map_macro(
+            &from_type,
+            meta_variant_names,
+            variant_names,
+            ref_mutability,
+        );
+        output_items.push(define_map_macro(&map_macro_name, rules, opts).into());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn generate_all_map_macros_for_type(
     type_name: &Ident,
-    num_generics: usize,
+    generics: &Generics,
     struct_names: &[Ident],
+    boxed: &[Boxing],
+    path_prefix: &TokenStream2,
     map_into: &Option<IdentList>,
-    generator: impl Fn(&MacroFromType, Option<&Ident>) -> TokenStream2,
+    opts: &StructOpts,
+    generator: impl Fn(&MacroFromType, Option<&Ident>) -> (Ident, TokenStream2),
     output_items: &mut Vec<TokenStream>,
 ) {
     let from_type = MacroFromType {
         name: type_name,
-        num_generics,
+        generics,
         struct_names,
+        boxed,
+        path_prefix,
     };
-    let map_macro_self = generator(&from_type, None);
-    output_items.push(map_macro_self.into());
+    let (map_macro_name, rules) = generator(&from_type, None);
+    output_items.push(define_map_macro(&map_macro_name, rules, opts).into());
 
     if let Some(map_into) = map_into {
         for to_type in &map_into.idents {
-            let map_macro_to = generator(&from_type, Some(to_type));
-            output_items.push(map_macro_to.into());
+            let (map_macro_name, rules) = generator(&from_type, Some(to_type));
+            output_items.push(define_map_macro(&map_macro_name, rules, opts).into());
+        }
+    }
+}
+
+/// Define a map macro with the given rules, making it available according to `map_macro_vis`.
+fn define_map_macro(
+    map_macro_name: &Ident,
+    rules: TokenStream2,
+    opts: &StructOpts,
+) -> TokenStream2 {
+    match opts.map_macro_vis {
+        None => quote! {
+            #[macro_export]
+            macro_rules! #map_macro_name { #rules }
+        },
+        Some(MapMacroVis::Module) => quote! {
+            macro_rules! #map_macro_name { #rules }
+            #[allow(unused_imports)]
+            use #map_macro_name;
+        },
+        Some(MapMacroVis::Crate) => quote! {
+            macro_rules! #map_macro_name { #rules }
+            #[allow(unused_imports)]
+            pub(crate) use #map_macro_name;
+        },
+        Some(MapMacroVis::Pub) => {
+            // A macro must be exported from the crate root in order to be re-exported publicly.
+            // Give it a hidden name there, which types with the same name tell apart using
+            // `map_macro_disambiguator` or the modules of their `map_macro_path`. Without either,
+            // the name suggests the fix in the error for a clash.
+            let disambiguator = opts.map_macro_disambiguator.clone().or_else(|| {
+                opts.map_macro_path
+                    .as_deref()
+                    .and_then(map_macro_path_disambiguator)
+            });
+            let exported_name = match disambiguator {
+                Some(disambiguator) => {
+                    format_ident!("__superstruct_{}_{}", disambiguator, map_macro_name)
+                }
+                None => format_ident!(
+                    "__superstruct_{}_use_map_macro_disambiguator_if_clashing",
+                    map_macro_name
+                ),
+            };
+            quote! {
+                #[doc(hidden)]
+                #[macro_export]
+                macro_rules! #exported_name { #rules }
+                pub use #exported_name as #map_macro_name;
+            }
         }
     }
 }
@@ -82,7 +229,7 @@ fn generate_owned_map_macro(
     from_type: &MacroFromType,
     to_type_name: Option<&Ident>,
     variant_names: &[Ident],
-) -> TokenStream2 {
+) -> (Ident, TokenStream2) {
     assert_eq!(
         from_type.struct_names.len(),
         variant_names.len(),
@@ -91,30 +238,71 @@ fn generate_owned_map_macro(
 
     let from_type_name = &from_type.name;
     let from_type_struct_names = from_type.struct_names;
+    let path_prefix = from_type.path_prefix;
     let to_type_name = to_type_name.unwrap_or(from_type_name);
     let map_macro_name = generate_map_macro_name(from_type_name, to_type_name);
 
     // Generics we want the compiler to infer.
-    let from_type_generics = underscore_generics(from_type.num_generics);
+    let from_type_generics = inferred_generics(from_type.generics);
 
-    quote! {
-        #[macro_export]
-        macro_rules! #map_macro_name {
-            ($value:expr, $f:expr) => {
+    let map_apply_name = map_apply_fn_name();
+    let map_apply_fn = generate_map_apply_fn();
+
+    let from_type_path = quote! { #path_prefix #from_type_name };
+    let inners = variant_names
+        .iter()
+        .zip(from_type.boxed)
+        .map(|(variant_name, boxing)| {
+            unboxed(quote! { inner }, *boxing, &from_type_path, variant_name)
+        })
+        .collect::<Vec<_>>();
+    let constructors = map_constructors(from_type, to_type_name, variant_names, false);
+
+    let closure_rules = generate_closure_rules(
+        quote! { $value:expr, },
+        quote! { $inner:pat_param, },
+        quote! {},
+        |bind_cons| {
+            let arms = generate_closure_map_arms(
+                from_type,
+                variant_names,
+                &inners,
+                &constructors,
+                bind_cons,
+            );
+            quote! { match $value { #arms } }
+        },
+    );
+
+    let apply_rules = generate_apply_rules(
+        quote! { $value:expr, },
+        quote! { $inner:pat_param, },
+        quote! { $inner, },
+        quote! {},
+        &constructors,
+        |f, cons_ty, constructors| {
+            quote! {
+                #map_apply_fn
                 match $value {
                     #(
-                        #from_type_name::#variant_names(inner) => {
-                            let f: fn(
-                                #from_type_struct_names #from_type_generics,
-                                fn(_) -> _,
-                            ) -> _ = $f;
-                            f(inner, #to_type_name::#variant_names)
+                        #path_prefix #from_type_name::#variant_names(inner) => {
+                            #map_apply_name::<
+                                #path_prefix #from_type_struct_names #from_type_generics,
+                                #cons_ty,
+                                _,
+                            >(#f, #inners, #constructors)
                         }
                     )*
                 }
             }
-        }
-    }
+        },
+    );
+
+    let rules = quote! {
+        #closure_rules
+        #apply_rules
+    };
+    (map_macro_name, rules)
 }
 
 fn generate_ref_map_macro(
@@ -122,7 +310,7 @@ fn generate_ref_map_macro(
     to_type_name: Option<&Ident>,
     variant_names: &[Ident],
     mutable: bool,
-) -> TokenStream2 {
+) -> (Ident, TokenStream2) {
     assert_eq!(
         from_type.struct_names.len(),
         variant_names.len(),
@@ -131,11 +319,12 @@ fn generate_ref_map_macro(
 
     let from_type_name = &from_type.name;
     let from_type_struct_names = from_type.struct_names;
+    let path_prefix = from_type.path_prefix;
     let to_type_name = to_type_name.unwrap_or(from_type_name);
     let map_macro_name = generate_map_macro_name(from_type_name, to_type_name);
 
     // Generics we want the compiler to infer.
-    let from_type_generics = underscore_generics(from_type.num_generics);
+    let from_type_generics = inferred_generics(from_type.generics);
 
     let mutability = if mutable {
         quote! { mut }
@@ -143,22 +332,503 @@ fn generate_ref_map_macro(
         quote! {}
     };
 
+    let map_apply_name = map_apply_fn_name();
+    let map_apply_fn = generate_map_apply_fn();
+
+    let inners = variant_names
+        .iter()
+        .map(|_| quote! { inner })
+        .collect::<Vec<_>>();
+    let constructors = map_constructors(from_type, to_type_name, variant_names, true);
+
+    let closure_rules = generate_closure_rules(
+        quote! { &$lifetime:tt _, $value:expr, },
+        quote! { $inner:pat_param, },
+        quote! {},
+        |bind_cons| {
+            let arms = generate_closure_map_arms(
+                from_type,
+                variant_names,
+                &inners,
+                &constructors,
+                bind_cons,
+            );
+            quote! { match $value { #arms } }
+        },
+    );
+
+    let apply_rules = generate_apply_rules(
+        quote! { &$lifetime:tt _, $value:expr, },
+        quote! { $inner:pat_param, },
+        quote! { $inner, },
+        quote! {},
+        &constructors,
+        |f, cons_ty, constructors| {
+            quote! {
+                #map_apply_fn
+                match $value {
+                    #(
+                        #path_prefix #from_type_name::#variant_names(inner) => {
+                            #map_apply_name::<
+                                &$lifetime #mutability #path_prefix #from_type_struct_names #from_type_generics,
+                                #cons_ty,
+                                _,
+                            >(#f, inner, #constructors)
+                        }
+                    )*
+                }
+            }
+        },
+    );
+
+    let rules = quote! {
+        #closure_rules
+        #apply_rules
+    };
+    (map_macro_name, rules)
+}
+
+/// Generate the constructors passed to the mapping function of a map macro.
+///
+/// It isn't known which variants of another type are boxed, so its `From` impls for the variant
+/// structs are used rather than its enum variants. For `Ref` and `RefMut` types the enum variants
+/// are used, as they are never boxed.
+///
+/// Unlike an enum variant, `From::from` doesn't fix the type of its argument, which is needed for
+/// e.g. `cons(inner.into())`. The argument is therefore also passed by reference to the variant of
+/// the other type's `Ref`, which is never boxed.
+fn map_constructors(
+    from_type: &MacroFromType,
+    to_type_name: &Ident,
+    variant_names: &[Ident],
+    is_ref: bool,
+) -> Vec<TokenStream2> {
+    let path_prefix = from_type.path_prefix;
+    let is_self = to_type_name == from_type.name;
+    let to_ref_type_name = format_ident!("{}Ref", to_type_name);
+    variant_names
+        .iter()
+        .zip(from_type.boxed)
+        .map(|(variant_name, boxing)| {
+            if is_ref || (is_self && *boxing == Boxing::Unboxed) {
+                quote! { #path_prefix #to_type_name::#variant_name }
+            } else {
+                quote! {
+                    |inner| {
+                        let _ = #path_prefix #to_ref_type_name::#variant_name(&inner);
+                        #path_prefix #to_type_name::from(inner)
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Generate the match arms for the `closure` form of a map macro.
+///
+/// Rather than coercing a closure to a function pointer, the closure's body is duplicated in each
+/// arm with its parameters bound to the variant and constructor. This allows the body to capture
+/// variables from its environment, while still being type-checked separately for each variant.
+///
+/// The `inners` are the expressions for each variant struct, given the binding `inner`.
+fn generate_closure_map_arms(
+    from_type: &MacroFromType,
+    variant_names: &[Ident],
+    inners: &[TokenStream2],
+    constructors: &[TokenStream2],
+    bind_cons: bool,
+) -> TokenStream2 {
+    let from_type_name = &from_type.name;
+    let path_prefix = from_type.path_prefix;
+    let cons_bindings = constructors
+        .iter()
+        .map(|constructor| closure_cons_binding(bind_cons, constructor.clone()));
     quote! {
-        #[macro_export]
-        macro_rules! #map_macro_name {
-            (&$lifetime:tt _, $value:expr, $f:expr) => {
+        #(
+            #path_prefix #from_type_name::#variant_names(inner) => {
+                let $inner = #inners;
+                #cons_bindings
+                $body
+            }
+        )*
+    }
+}
+
+/// Generate the rules for the `closure` form of a map macro.
+///
+/// The rules match `prefix closure(|closure_params cons| body) suffix`, and expand to the
+/// expression returned by `match_expr`. If the constructor parameter is `_` then it is not bound
+/// at all, as an unused constructor can't have its generic arguments inferred.
+fn generate_closure_rules(
+    prefix: TokenStream2,
+    closure_params: TokenStream2,
+    suffix: TokenStream2,
+    match_expr: impl Fn(bool) -> TokenStream2,
+) -> TokenStream2 {
+    let without_cons = match_expr(false);
+    let with_cons = match_expr(true);
+    quote! {
+        (#prefix closure($(move)? |#closure_params _| $body:expr) #suffix) => {
+            #without_cons
+        };
+        (#prefix closure($(move)? |#closure_params $cons:pat_param| $body:expr) #suffix) => {
+            #with_cons
+        };
+    }
+}
+
+/// Generate the rules for the function form of a map macro.
+///
+/// The rules match `prefix f suffix`, and expand to the expression returned by `apply_expr` given
+/// the mapping function, the type of its constructor parameter and the constructors.
+///
+/// If `f` is a closure whose constructor parameter is `_` then it is passed `()` rather than the
+/// constructor, as an unused constructor can't have its generic arguments inferred. The
+/// `closure_args` re-emit the parameters matched by `closure_params`.
+fn generate_apply_rules(
+    prefix: TokenStream2,
+    closure_params: TokenStream2,
+    closure_args: TokenStream2,
+    suffix: TokenStream2,
+    constructors: &[TokenStream2],
+    apply_expr: impl Fn(TokenStream2, TokenStream2, &[TokenStream2]) -> TokenStream2,
+) -> TokenStream2 {
+    let units = vec![quote! { () }; constructors.len()];
+    let without_cons = apply_expr(quote! { |#closure_args _| $body }, quote! { () }, &units);
+    let without_cons_ret = apply_expr(
+        quote! { |#closure_args _| -> $ret { $($body)* } },
+        quote! { () },
+        &units,
+    );
+    let with_cons = apply_expr(quote! { $f }, quote! { fn(_) -> _ }, constructors);
+    quote! {
+        (#prefix $(move)? |#closure_params _| -> $ret:ty { $($body:tt)* } #suffix) => {{
+            #without_cons_ret
+        }};
+        (#prefix $(move)? |#closure_params _| $body:expr #suffix) => {{
+            #without_cons
+        }};
+        (#prefix $f:expr #suffix) => {{
+            #with_cons
+        }};
+    }
+}
+
+fn closure_cons_binding(bind_cons: bool, constructor: TokenStream2) -> TokenStream2 {
+    if bind_cons {
+        quote! { let $cons = #constructor; }
+    } else {
+        quote! {}
+    }
+}
+
+/// Generate a macro which maps over two values at once, if they are the same variant.
+///
+/// If `ref_mutability` is set then the macro is for a `Ref` (`false`) or `RefMut` (`true`) type,
+/// and takes a lifetime argument like the other reference map macros.
+fn generate_pair_map_macro(
+    from_type: &MacroFromType,
+    variant_names: &[Ident],
+    ref_mutability: Option<bool>,
+) -> (Ident, TokenStream2) {
+    assert_eq!(
+        from_type.struct_names.len(),
+        variant_names.len(),
+        "there must be one struct per variant"
+    );
+
+    let type_name = &from_type.name;
+    let struct_names = from_type.struct_names;
+    let path_prefix = from_type.path_prefix;
+    let map_macro_name = generate_map_pair_macro_name(type_name);
+
+    // Generics we want the compiler to infer.
+    let struct_generics = inferred_generics(from_type.generics);
+
+    let (lifetime_arg, reference) = match ref_mutability {
+        None => (quote! {}, quote! {}),
+        Some(false) => (quote! { &$lifetime:tt _, }, quote! { &$lifetime }),
+        Some(true) => (quote! { &$lifetime:tt _, }, quote! { &$lifetime mut }),
+    };
+
+    let map_apply_name = map_pair_apply_fn_name();
+    let map_apply_fn = generate_map_pair_apply_fn();
+
+    let type_path = quote! { #path_prefix #type_name };
+    let (inners_a, inners_b): (Vec<_>, Vec<_>) = variant_names
+        .iter()
+        .zip(from_type.boxed)
+        .map(|(variant_name, boxing)| {
+            (
+                unboxed(quote! { inner_a }, *boxing, &type_path, variant_name),
+                unboxed(quote! { inner_b }, *boxing, &type_path, variant_name),
+            )
+        })
+        .unzip();
+    let constructors = map_constructors(
+        from_type,
+        type_name,
+        variant_names,
+        ref_mutability.is_some(),
+    );
+
+    let closure_rules = generate_closure_rules(
+        quote! { #lifetime_arg $a:expr, $b:expr, },
+        quote! { $inner_a:pat_param, $inner_b:pat_param, },
+        quote! { , $mismatch:expr },
+        |bind_cons| {
+            let cons_bindings = constructors
+                .iter()
+                .map(|constructor| closure_cons_binding(bind_cons, constructor.clone()));
+            quote! {
+                match ($a, $b) {
+                    #(
+                        (
+                            #path_prefix #type_name::#variant_names(inner_a),
+                            #path_prefix #type_name::#variant_names(inner_b),
+                        ) => {
+                            let $inner_a = #inners_a;
+                            let $inner_b = #inners_b;
+                            #cons_bindings
+                            $body
+                        }
+                    )*
+                    #[allow(unreachable_patterns)]
+                    (a, b) => ($mismatch)(a, b),
+                }
+            }
+        },
+    );
+
+    let apply_rules = generate_apply_rules(
+        quote! { #lifetime_arg $a:expr, $b:expr, },
+        quote! { $inner_a:pat_param, $inner_b:pat_param, },
+        quote! { $inner_a, $inner_b, },
+        quote! { , $mismatch:expr },
+        &constructors,
+        |f, cons_ty, constructors| {
+            quote! {
+                #map_apply_fn
+                match ($a, $b) {
+                    #(
+                        (
+                            #path_prefix #type_name::#variant_names(inner_a),
+                            #path_prefix #type_name::#variant_names(inner_b),
+                        ) => {
+                            #map_apply_name::<
+                                #reference #path_prefix #struct_names #struct_generics,
+                                #reference #path_prefix #struct_names #struct_generics,
+                                #cons_ty,
+                                _,
+                            >(#f, #inners_a, #inners_b, #constructors)
+                        }
+                    )*
+                    #[allow(unreachable_patterns)]
+                    (a, b) => ($mismatch)(a, b),
+                }
+            }
+        },
+    );
+
+    let rules = quote! {
+        #closure_rules
+        #apply_rules
+    };
+    (map_macro_name, rules)
+}
+
+/// Generate a macro which maps over the leaf variant structs of a meta variant type.
+///
+/// For the top-level enum the constructor rebuilds the top-level enum from the leaf struct. A
+/// `Ref` or `RefMut` to a leaf struct can't be wrapped back up into the top-level `Ref` or
+/// `RefMut`, so for these the constructor is for the inner enum's `Ref` or `RefMut` instead.
+fn generate_leaf_map_macro(
+    from_type: &MacroFromType,
+    meta_variant_names: &[Ident],
+    variant_names: &[Ident],
+    ref_mutability: Option<bool>,
+) -> (Ident, TokenStream2) {
+    assert_eq!(
+        from_type.struct_names.len(),
+        meta_variant_names.len(),
+        "there must be one inner enum per meta variant"
+    );
+
+    let type_name = &from_type.name;
+    let path_prefix = from_type.path_prefix;
+    let map_macro_name = generate_map_leaf_macro_name(type_name);
+
+    // Generics we want the compiler to infer.
+    let struct_generics = inferred_generics(from_type.generics);
+
+    let (lifetime_arg, reference) = match ref_mutability {
+        None => (quote! {}, quote! {}),
+        Some(false) => (quote! { &$lifetime:tt _, }, quote! { &$lifetime }),
+        Some(true) => (quote! { &$lifetime:tt _, }, quote! { &$lifetime mut }),
+    };
+
+    // One entry per leaf struct, for all combinations of meta variant and variant.
+    let mut patterns = vec![];
+    let mut leaf_struct_names = vec![];
+    let mut inners = vec![];
+    let mut constructors = vec![];
+    for (meta_variant_name, inner_enum_name) in
+        meta_variant_names.iter().zip(from_type.struct_names)
+    {
+        let inner_enum_path = quote! { #path_prefix #inner_enum_name };
+        for (variant_name, boxing) in variant_names.iter().zip(from_type.boxed) {
+            patterns.push(quote! {
+                #path_prefix #type_name::#meta_variant_name(
+                    #path_prefix #inner_enum_name::#variant_name(inner)
+                )
+            });
+            leaf_struct_names.push(format_ident!("{}{}", inner_enum_name, variant_name));
+            inners.push(unboxed(
+                quote! { inner },
+                *boxing,
+                &inner_enum_path,
+                variant_name,
+            ));
+            constructors.push(match ref_mutability {
+                None => {
+                    let inner = match boxing {
+                        Boxing::Unboxed => quote! { inner },
+                        Boxing::Boxed => quote! { ::std::boxed::Box::new(inner) },
+                        Boxing::Auto => quote! { ::core::convert::From::from(inner) },
+                    };
+                    quote! {
+                        |inner| #path_prefix #type_name::#meta_variant_name(
+                            #path_prefix #inner_enum_name::#variant_name(#inner)
+                        )
+                    }
+                }
+                Some(mutable) => {
+                    let inner_ref_name = if mutable {
+                        format_ident!("{}RefMut", inner_enum_name)
+                    } else {
+                        format_ident!("{}Ref", inner_enum_name)
+                    };
+                    quote! { #path_prefix #inner_ref_name::#variant_name }
+                }
+            });
+        }
+    }
+
+    let map_apply_name = map_apply_fn_name();
+    let map_apply_fn = generate_map_apply_fn();
+
+    let closure_rules = generate_closure_rules(
+        quote! { #lifetime_arg $value:expr, },
+        quote! { $inner:pat_param, },
+        quote! {},
+        |bind_cons| {
+            let cons_bindings = constructors
+                .iter()
+                .map(|constructor| closure_cons_binding(bind_cons, constructor.clone()));
+            quote! {
                 match $value {
                     #(
-                        #from_type_name::#variant_names(inner) => {
-                            let f: fn(
-                                &$lifetime #mutability #from_type_struct_names #from_type_generics,
-                                fn(_) -> _,
-                            ) -> _ = $f;
-                            f(inner, #to_type_name::#variant_names)
+                        #patterns => {
+                            let $inner = #inners;
+                            #cons_bindings
+                            $body
                         }
                     )*
                 }
             }
+        },
+    );
+
+    let apply_rules = generate_apply_rules(
+        quote! { #lifetime_arg $value:expr, },
+        quote! { $inner:pat_param, },
+        quote! { $inner, },
+        quote! {},
+        &constructors,
+        |f, cons_ty, constructors| {
+            quote! {
+                #map_apply_fn
+                match $value {
+                    #(
+                        #patterns => {
+                            #map_apply_name::<
+                                #reference #path_prefix #leaf_struct_names #struct_generics,
+                                #cons_ty,
+                                _,
+                            >(#f, #inners, #constructors)
+                        }
+                    )*
+                }
+            }
+        },
+    );
+
+    let rules = quote! {
+        #closure_rules
+        #apply_rules
+    };
+    (map_macro_name, rules)
+}
+
+/// Parse a path like `crate::types` or `$crate::types` into a prefix for type names.
+fn parse_map_macro_path(path: &str) -> TokenStream2 {
+    // `$crate` isn't valid in a path outside of a macro, so handle it separately.
+    let (dollar_crate, rest) = match path.strip_prefix("$crate") {
+        Some(rest) => (quote! { $crate }, rest),
+        None => (quote! {}, path),
+    };
+    let rest = if rest.is_empty() {
+        quote! {}
+    } else {
+        let path: syn::Path = syn::parse_str(rest).expect("map_macro_path not valid");
+        quote! { #path }
+    };
+    quote! { #dollar_crate #rest :: }
+}
+
+/// Join the module names in a `map_macro_path` like `crate::a::b` into an identifier like `a_b`.
+///
+/// Return `None` if the path doesn't name any modules.
+fn map_macro_path_disambiguator(path: &str) -> Option<Ident> {
+    let modules = path
+        .split("::")
+        .map(str::trim)
+        .filter(|segment| !matches!(*segment, "$crate" | "crate" | "self" | "super" | ""))
+        .collect::<Vec<_>>();
+    (!modules.is_empty()).then(|| format_ident!("{}", modules.join("_")))
+}
+
+fn map_pair_apply_fn_name() -> Ident {
+    format_ident!("__superstruct_map_pair_apply")
+}
+
+fn map_apply_fn_name() -> Ident {
+    format_ident!("__superstruct_map_apply")
+}
+
+/// Generate a helper function which applies the mapping function to a variant and constructor.
+///
+/// The mapping function is coerced to a function pointer whose argument types are given by the
+/// turbofish at each call site. Unlike a `let` binding with a function pointer type, placeholder
+/// lifetimes like `'_` in a turbofish are inferred rather than higher-ranked, so this works for
+/// types with lifetime parameters.
+fn generate_map_apply_fn() -> TokenStream2 {
+    let map_apply_name = map_apply_fn_name();
+    quote! {
+        #[inline(always)]
+        fn #map_apply_name<I, C, R>(f: fn(I, C) -> R, inner: I, cons: C) -> R {
+            f(inner, cons)
+        }
+    }
+}
+
+/// Generate a helper function like the one from `generate_map_apply_fn`, for pairs of variants.
+fn generate_map_pair_apply_fn() -> TokenStream2 {
+    let map_apply_name = map_pair_apply_fn_name();
+    quote! {
+        #[inline(always)]
+        fn #map_apply_name<A, B, C, R>(f: fn(A, B, C) -> R, a: A, b: B, cons: C) -> R {
+            f(a, b, cons)
         }
     }
 }
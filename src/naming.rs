@@ -13,3 +13,64 @@ pub fn generate_map_macro_name(from_type_name: &Ident, to_type_name: &Ident) ->
         )
     }
 }
+
+pub fn generate_map_pair_macro_name(type_name: &Ident) -> Ident {
+    format_ident!("map_{}_pair", snake_case(&type_name.to_string()))
+}
+
+pub fn generate_map_leaf_macro_name(type_name: &Ident) -> Ident {
+    format_ident!("map_{}_leaf", snake_case(&type_name.to_string()))
+}
+
+pub fn generate_variant_trait_name(type_name: &Ident) -> Ident {
+    format_ident!("{}Variant", type_name)
+}
+
+pub fn generate_visitor_trait_names(type_name: &Ident) -> (Ident, Ident, Ident) {
+    (
+        format_ident!("{}Visitor", type_name),
+        format_ident!("{}VisitorMut", type_name),
+        format_ident!("{}Folder", type_name),
+    )
+}
+
+pub fn generate_kind_enum_name(type_name: &Ident) -> Ident {
+    format_ident!("{}Kind", type_name)
+}
+
+/// The name of the helper macro for `superstruct_impl` and `delegate`, which is made available
+/// under the names of the top-level enum, `Ref` and `RefMut` so that it is imported along with them.
+pub fn generate_impl_helper_macro_name(type_name: &Ident) -> Ident {
+    format_ident!("__superstruct_impl_{}", type_name)
+}
+
+/// The name of the helper macro for a `delegatable` trait, which is made available under the name
+/// of the trait so that it is imported along with it.
+pub fn generate_delegatable_macro_name(trait_name: &Ident) -> Ident {
+    format_ident!("__superstruct_delegatable_{}", trait_name)
+}
+
+pub fn generate_seed_name(type_name: &Ident) -> Ident {
+    format_ident!("{}Seed", type_name)
+}
+
+/// The name of the hidden method of an enum which moves the variant struct for `variant_name` out
+/// of its (possibly boxed) field, or borrows it if `ref_mutability` is given.
+pub fn generate_unbox_method_name(variant_name: &Ident, ref_mutability: Option<bool>) -> Ident {
+    let suffix = match ref_mutability {
+        None => "",
+        Some(false) => "_ref",
+        Some(true) => "_mut",
+    };
+    format_ident!(
+        "__superstruct_unbox_{}{}",
+        snake_case(&variant_name.to_string()),
+        suffix
+    )
+}
+
+/// The name of the hidden trait which selects whether the variants of `type_name` are boxed,
+/// depending on their size.
+pub fn generate_box_selector_trait_name(type_name: &Ident) -> Ident {
+    format_ident!("__SuperstructBox{}", type_name)
+}
@@ -0,0 +1,252 @@
+//! Generate implementations of standard traits for the `Ref` type which compare by value, with the
+//! `ref_traits` option.
+use crate::utils::with_variant_bounds;
+use proc_macro2::{Literal, Span};
+use quote::quote;
+use syn::{parenthesized, token::Paren, Attribute, Generics, Ident};
+
+/// The traits which may be listed in `ref_traits`.
+const REF_TRAITS: [&str; 6] = ["Debug", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord"];
+
+/// The integer types which may be given as the representation of an enum.
+const DISCRIMINANT_TYPES: [&str; 12] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// The type of the discriminant of the top-level enum with the attributes `enum_attrs`, which is
+/// `isize` unless it has a primitive representation like `#[repr(u8)]`.
+fn discriminant_type(enum_attrs: &[Attribute]) -> Ident {
+    let mut discriminant_type = None;
+    for attr in enum_attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+    {
+        // Representations which aren't integer types, like `align(8)`, are skipped.
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.input.peek(Paren) {
+                let _content;
+                parenthesized!(_content in meta.input);
+            } else if let Some(ident) = meta.path.get_ident() {
+                if DISCRIMINANT_TYPES.contains(&ident.to_string().as_str()) {
+                    discriminant_type = Some(ident.clone());
+                }
+            }
+            Ok(())
+        });
+    }
+    discriminant_type.unwrap_or_else(|| Ident::new("isize", Span::call_site()))
+}
+
+/// Generate the implementations of the `ref_traits` for the `Ref` type.
+///
+/// The `generics` are the generics of the top-level enum, and `ref_generics` those of the `Ref`.
+/// The `enum_attrs` are the attributes of the top-level enum, which may set its representation.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_ref_trait_impls(
+    type_name: &Ident,
+    ref_type_name: &Ident,
+    generics: &Generics,
+    ref_generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+    ref_traits: &[Ident],
+    enum_attrs: &[Attribute],
+) -> proc_macro2::TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let (ref_impl_generics, ref_ty_generics, _) = ref_generics.split_for_impl();
+    let bounds = |bound: proc_macro2::TokenStream| {
+        with_variant_bounds(
+            ref_generics.where_clause.as_ref(),
+            struct_names,
+            &ty_generics,
+            bound,
+        )
+    };
+    let ref_type = quote! { #ref_type_name #ref_ty_generics };
+    let enum_type = quote! { #type_name #ty_generics };
+
+    // The index of each variant, which is used by the orderings.
+    let variant_indices = 0..variant_names.len();
+    let variant_index = quote! {
+        |value: &#ref_type| match value {
+            #(
+                #ref_type_name::#variant_names(_) => #variant_indices,
+            )*
+        }
+    };
+
+    let impls = ref_traits.iter().map(|ref_trait| {
+        match ref_trait.to_string().as_str() {
+            "Debug" => {
+                let where_clause = bounds(quote! { ::core::fmt::Debug });
+                let variant_strs = variant_names.iter().map(ToString::to_string);
+                quote! {
+                    impl #ref_impl_generics ::core::fmt::Debug for #ref_type #where_clause {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                            match self {
+                                #(
+                                    Self::#variant_names(inner) => {
+                                        f.debug_tuple(#variant_strs).field(inner).finish()
+                                    }
+                                )*
+                            }
+                        }
+                    }
+                }
+            }
+            "PartialEq" => {
+                let where_clause = bounds(quote! { ::core::cmp::PartialEq });
+                let (impl_generics, _, _) = generics.split_for_impl();
+                let enum_where_clause = with_variant_bounds(
+                    generics.where_clause.as_ref(),
+                    struct_names,
+                    &ty_generics,
+                    quote! { ::core::cmp::PartialEq },
+                );
+                quote! {
+                    impl #ref_impl_generics ::core::cmp::PartialEq for #ref_type #where_clause {
+                        fn eq(&self, other: &Self) -> bool {
+                            match (self, other) {
+                                #(
+                                    (Self::#variant_names(a), Self::#variant_names(b)) => {
+                                        ::core::cmp::PartialEq::eq(*a, *b)
+                                    }
+                                )*
+                                #[allow(unreachable_patterns)]
+                                _ => false,
+                            }
+                        }
+                    }
+
+                    impl #ref_impl_generics ::core::cmp::PartialEq<#enum_type> for #ref_type
+                    #where_clause
+                    {
+                        fn eq(&self, other: &#enum_type) -> bool {
+                            ::core::cmp::PartialEq::eq(self, &other.to_ref())
+                        }
+                    }
+
+                    impl #ref_impl_generics ::core::cmp::PartialEq<#ref_type> for #enum_type
+                    #where_clause
+                    {
+                        fn eq(&self, other: &#ref_type) -> bool {
+                            ::core::cmp::PartialEq::eq(&self.to_ref(), other)
+                        }
+                    }
+
+                    #(
+                        impl #impl_generics ::core::cmp::PartialEq<#struct_names #ty_generics>
+                            for #enum_type #enum_where_clause
+                        {
+                            fn eq(&self, other: &#struct_names #ty_generics) -> bool {
+                                match self {
+                                    Self::#variant_names(inner) => {
+                                        <#struct_names #ty_generics as ::core::cmp::PartialEq>::eq(
+                                            inner, other,
+                                        )
+                                    }
+                                    #[allow(unreachable_patterns)]
+                                    _ => false,
+                                }
+                            }
+                        }
+                    )*
+                }
+            }
+            "Eq" => {
+                let where_clause = bounds(quote! { ::core::cmp::Eq });
+                quote! {
+                    impl #ref_impl_generics ::core::cmp::Eq for #ref_type #where_clause {}
+                }
+            }
+            "Hash" => {
+                let where_clause = bounds(quote! { ::core::hash::Hash });
+                // Hash the discriminant and then the variant struct, like a derived implementation
+                // for the top-level enum, which only hashes the discriminant if there is more than
+                // one variant. The discriminant of the `Ref` may have a different type, so the
+                // variant index is hashed as the discriminant type of the top-level enum.
+                let discriminant_type = discriminant_type(enum_attrs);
+                let discriminants = (0..variant_names.len()).map(Literal::usize_unsuffixed);
+                let hash_discriminant = (variant_names.len() > 1).then(|| {
+                    quote! {
+                        let discriminant: #discriminant_type = match self {
+                            #(
+                                Self::#variant_names(_) => #discriminants,
+                            )*
+                        };
+                        ::core::hash::Hash::hash(&discriminant, state);
+                    }
+                });
+                quote! {
+                    impl #ref_impl_generics ::core::hash::Hash for #ref_type #where_clause {
+                        fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                            #hash_discriminant
+                            match self {
+                                #(
+                                    Self::#variant_names(inner) => {
+                                        ::core::hash::Hash::hash(*inner, state)
+                                    }
+                                )*
+                            }
+                        }
+                    }
+                }
+            }
+            "PartialOrd" => {
+                let where_clause = bounds(quote! { ::core::cmp::PartialOrd });
+                quote! {
+                    impl #ref_impl_generics ::core::cmp::PartialOrd for #ref_type #where_clause {
+                        fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                            match (self, other) {
+                                #(
+                                    (Self::#variant_names(a), Self::#variant_names(b)) => {
+                                        ::core::cmp::PartialOrd::partial_cmp(*a, *b)
+                                    }
+                                )*
+                                #[allow(unreachable_patterns)]
+                                _ => {
+                                    let variant_index = #variant_index;
+                                    ::core::cmp::PartialOrd::partial_cmp(
+                                        &variant_index(self),
+                                        &variant_index(other),
+                                    )
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            "Ord" => {
+                let where_clause = bounds(quote! { ::core::cmp::Ord });
+                quote! {
+                    impl #ref_impl_generics ::core::cmp::Ord for #ref_type #where_clause {
+                        fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                            match (self, other) {
+                                #(
+                                    (Self::#variant_names(a), Self::#variant_names(b)) => {
+                                        ::core::cmp::Ord::cmp(*a, *b)
+                                    }
+                                )*
+                                #[allow(unreachable_patterns)]
+                                _ => {
+                                    let variant_index = #variant_index;
+                                    ::core::cmp::Ord::cmp(&variant_index(self), &variant_index(other))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => panic!(
+                "unsupported trait in `ref_traits`: {ref_trait}, expected one of: {}",
+                REF_TRAITS.join(", ")
+            ),
+        }
+    });
+
+    quote! {
+        #(
+            #impls
+        )*
+    }
+}
@@ -0,0 +1,488 @@
+//! Generate serde implementations for the top-level enum and `Ref`, with the `serde` and
+//! `untagged_deserialize` options.
+//!
+//! The generated code refers to the `serde` crate, which must be a dependency of the crate using
+//! the macro.
+use crate::naming::{generate_kind_enum_name, generate_seed_name};
+use crate::utils::with_variant_bounds;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Generics, Ident, Type, Visibility};
+
+/// Implement `Serialize` for the top-level enum or `Ref` by serializing the variant struct.
+///
+/// The `generics` are the generics of `type_name`, while `struct_generics` are the generics of the
+/// variant structs, which are required to implement `Serialize`.
+pub fn generate_serialize_impl(
+    type_name: &Ident,
+    generics: &Generics,
+    struct_generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+    let where_clause = with_variant_bounds(
+        generics.where_clause.as_ref(),
+        struct_names,
+        &struct_ty_generics,
+        quote! { ::serde::Serialize },
+    );
+
+    quote! {
+        impl #impl_generics ::serde::Serialize for #type_name #ty_generics #where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> Result<__S::Ok, __S::Error>
+            where
+                __S: ::serde::Serializer,
+            {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => ::serde::Serialize::serialize(inner, serializer),
+                    )*
+                }
+            }
+        }
+    }
+}
+
+/// Generate `deserialize_as` for the top-level enum, and the `FooSeed` type which deserializes a
+/// given kind of variant.
+pub fn generate_deserialize_as(
+    type_name: &Ident,
+    visibility: &Visibility,
+    generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+) -> proc_macro2::TokenStream {
+    let kind_name = generate_kind_enum_name(type_name);
+    let seed_name = generate_seed_name(type_name);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let deserialize_bounds = with_variant_bounds(
+        None,
+        struct_names,
+        &ty_generics,
+        quote! { ::serde::Deserialize<'de> },
+    );
+
+    let mut seed_impl_generics = generics.clone();
+    seed_impl_generics.params.insert(0, parse_quote!('de));
+    let (seed_impl_generics, _, _) = seed_impl_generics.split_for_impl();
+    let seed_where_clause = with_variant_bounds(
+        generics.where_clause.as_ref(),
+        struct_names,
+        &ty_generics,
+        quote! { ::serde::Deserialize<'de> },
+    );
+
+    let seed_doc = format!(
+        "Deserializes the kind of variant of [`{type_name}`] given by `kind`, for use with \
+        `serde::de::DeserializeSeed`."
+    );
+
+    quote! {
+        impl #impl_generics #type_name #ty_generics #where_clause {
+            /// Deserialize the variant struct of the given `kind`.
+            pub fn deserialize_as<'de, __D>(
+                kind: #kind_name,
+                deserializer: __D,
+            ) -> Result<Self, __D::Error>
+            #deserialize_bounds
+                __D: ::serde::Deserializer<'de>,
+            {
+                match kind {
+                    #(
+                        #kind_name::#variant_names => {
+                            <#struct_names #ty_generics as ::serde::Deserialize>::deserialize(
+                                deserializer,
+                            )
+                            .map(Self::from)
+                        }
+                    )*
+                }
+            }
+        }
+
+        #[doc = #seed_doc]
+        #visibility struct #seed_name #generics #where_clause {
+            pub kind: #kind_name,
+            _phantom: ::core::marker::PhantomData<fn() -> #type_name #ty_generics>,
+        }
+
+        impl #impl_generics #seed_name #ty_generics #where_clause {
+            pub fn new(kind: #kind_name) -> Self {
+                Self {
+                    kind,
+                    _phantom: ::core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl #impl_generics From<#kind_name> for #seed_name #ty_generics #where_clause {
+            fn from(kind: #kind_name) -> Self {
+                Self::new(kind)
+            }
+        }
+
+        impl #seed_impl_generics ::serde::de::DeserializeSeed<'de> for #seed_name #ty_generics
+        #seed_where_clause
+        {
+            type Value = #type_name #ty_generics;
+
+            fn deserialize<__D>(self, deserializer: __D) -> Result<Self::Value, __D::Error>
+            where
+                __D: ::serde::Deserializer<'de>,
+            {
+                #type_name::deserialize_as(self.kind, deserializer)
+            }
+        }
+    }
+}
+
+/// Implement `Deserialize` for the top-level enum by trying each variant in turn.
+///
+/// The input is first deserialized into the self-describing `buffer` type, which must implement
+/// `Deserializer` and `Clone`, and each variant is deserialized from a clone of it. The variants
+/// should be ordered from most to least specific. If `strict` is set, then a variant is rejected
+/// if the input has fields which it doesn't.
+pub fn generate_untagged_deserialize(
+    type_name: &Ident,
+    generics: &Generics,
+    variant_names: &[&Ident],
+    struct_names: &[&Ident],
+    strict: bool,
+    buffer: &Type,
+) -> proc_macro2::TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut impl_generics = generics.clone();
+    impl_generics.params.insert(0, parse_quote!('de));
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let where_clause = with_variant_bounds(
+        generics.where_clause.as_ref(),
+        struct_names,
+        &ty_generics,
+        quote! { ::serde::Deserialize<'de> },
+    );
+
+    let variant_strs = variant_names.iter().map(|name| name.to_string());
+    let no_match_message = format!("data did not match any variant of `{type_name}`: {{}}");
+    let (strict_deserializer, wrap) = if strict {
+        (generate_strict_deserializer(), quote! { __Strict })
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    quote! {
+        impl #impl_generics ::serde::Deserialize<'de> for #type_name #ty_generics #where_clause {
+            fn deserialize<__D>(deserializer: __D) -> Result<Self, __D::Error>
+            where
+                __D: ::serde::Deserializer<'de>,
+            {
+                #strict_deserializer
+
+                let buffer = <#buffer as ::serde::Deserialize>::deserialize(deserializer)?;
+                let mut errors = ::std::vec::Vec::new();
+                #(
+                    match <#struct_names #ty_generics as ::serde::Deserialize>::deserialize(
+                        #wrap(::core::clone::Clone::clone(&buffer)),
+                    ) {
+                        Ok(inner) => return Ok(Self::from(inner)),
+                        Err(error) => {
+                            errors.push(::std::format!("{}: {}", #variant_strs, error));
+                        }
+                    }
+                )*
+                Err(<__D::Error as ::serde::de::Error>::custom(::std::format!(
+                    #no_match_message,
+                    errors.join("; "),
+                )))
+            }
+        }
+    }
+}
+
+/// Generate the `__Strict` deserializer, which forwards to the deserializer it wraps, but rejects
+/// structs with fields that aren't in the list of fields passed to `deserialize_struct`.
+///
+/// Only the top-level struct is checked. Its fields can't be checked if it is deserialized as a
+/// map, e.g. due to `serde(flatten)`, so maps are rejected.
+fn generate_strict_deserializer() -> proc_macro2::TokenStream {
+    let forward_methods = [
+        "any",
+        "bool",
+        "i8",
+        "i16",
+        "i32",
+        "i64",
+        "i128",
+        "u8",
+        "u16",
+        "u32",
+        "u64",
+        "u128",
+        "f32",
+        "f64",
+        "char",
+        "str",
+        "string",
+        "bytes",
+        "byte_buf",
+        "option",
+        "unit",
+        "seq",
+        "identifier",
+        "ignored_any",
+    ]
+    .iter()
+    .map(|name| format_ident!("deserialize_{}", name));
+
+    quote! {
+        struct __Strict<__B>(__B);
+
+        impl<'de, __B> ::serde::Deserializer<'de> for __Strict<__B>
+        where
+            __B: ::serde::Deserializer<'de> + ::core::clone::Clone,
+        {
+            type Error = <__B as ::serde::Deserializer<'de>>::Error;
+
+            #(
+                fn #forward_methods<__V>(self, visitor: __V) -> Result<__V::Value, Self::Error>
+                where
+                    __V: ::serde::de::Visitor<'de>,
+                {
+                    self.0.#forward_methods(visitor)
+                }
+            )*
+
+            fn deserialize_map<__V>(self, _visitor: __V) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                Err(<Self::Error as ::serde::de::Error>::custom(
+                    "strict untagged deserialization requires a struct, not a map",
+                ))
+            }
+
+            fn deserialize_unit_struct<__V>(
+                self,
+                name: &'static str,
+                visitor: __V,
+            ) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                self.0.deserialize_unit_struct(name, visitor)
+            }
+
+            fn deserialize_newtype_struct<__V>(
+                self,
+                name: &'static str,
+                visitor: __V,
+            ) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                self.0.deserialize_newtype_struct(name, visitor)
+            }
+
+            fn deserialize_tuple<__V>(
+                self,
+                len: usize,
+                visitor: __V,
+            ) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                self.0.deserialize_tuple(len, visitor)
+            }
+
+            fn deserialize_tuple_struct<__V>(
+                self,
+                name: &'static str,
+                len: usize,
+                visitor: __V,
+            ) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                self.0.deserialize_tuple_struct(name, len, visitor)
+            }
+
+            fn deserialize_enum<__V>(
+                self,
+                name: &'static str,
+                variants: &'static [&'static str],
+                visitor: __V,
+            ) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                self.0.deserialize_enum(name, variants, visitor)
+            }
+
+            fn deserialize_struct<__V>(
+                self,
+                name: &'static str,
+                fields: &'static [&'static str],
+                visitor: __V,
+            ) -> Result<__V::Value, Self::Error>
+            where
+                __V: ::serde::de::Visitor<'de>,
+            {
+                let keys = <::std::collections::BTreeMap<
+                    ::std::string::String,
+                    ::serde::de::IgnoredAny,
+                > as ::serde::Deserialize>::deserialize(self.0.clone())?;
+                if let Some(key) = keys.keys().find(|key| !fields.contains(&key.as_str())) {
+                    return Err(<Self::Error as ::serde::de::Error>::unknown_field(key, fields));
+                }
+                self.0.deserialize_struct(name, fields, visitor)
+            }
+
+            fn is_human_readable(&self) -> bool {
+                self.0.is_human_readable()
+            }
+        }
+    }
+}
+
+/// The serde attributes of a local enum which mirrors the top-level enum.
+pub struct SerdeAttributes {
+    /// The contents of the `serde(..)` attribute of the enum, e.g. `tag = "version"`.
+    pub container: Vec<proc_macro2::TokenStream>,
+    /// The contents of the `serde(..)` attribute of each variant, e.g. `rename = "v1"`.
+    pub variants: Vec<Vec<proc_macro2::TokenStream>>,
+}
+
+/// The type which a mirrored `Serialize` implementation is for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SerializeFor {
+    Enum,
+    Ref,
+    RefMut,
+}
+
+/// Generate a local enum named `type_name` with one generic newtype variant per variant, which
+/// derives `derive` with the `serde_attributes`.
+///
+/// It is named after the top-level enum so that it is serialized with the same name, and so that
+/// error messages refer to it.
+fn generate_mirror_enum(
+    type_name: &Ident,
+    variant_names: &[Ident],
+    serde_attributes: &SerdeAttributes,
+    derive: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let type_params = (0..variant_names.len())
+        .map(|i| format_ident!("__T{}", i))
+        .collect::<Vec<_>>();
+    let container_attributes = &serde_attributes.container;
+    let variant_attributes = &serde_attributes.variants;
+    quote! {
+        #[derive(#derive)]
+        #[serde(#(#container_attributes),*)]
+        enum #type_name<#(#type_params),*> {
+            #(
+                #[serde(#(#variant_attributes),*)]
+                #variant_names(#type_params),
+            )*
+        }
+    }
+}
+
+/// Implement `Serialize` for the top-level enum, `Ref` or `RefMut` named `impl_type_name`, by
+/// serializing a reference to the variant struct as a variant of a mirror of the top-level enum.
+///
+/// The `generics` are the generics of `impl_type_name`, while `struct_generics` are the generics
+/// of the variant structs.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_mirror_serialize_impl(
+    type_name: &Ident,
+    impl_type_name: &Ident,
+    generics: &Generics,
+    struct_generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+    serde_attributes: &SerdeAttributes,
+    serialize_for: SerializeFor,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+    let where_clause = with_variant_bounds(
+        generics.where_clause.as_ref(),
+        struct_names,
+        &struct_ty_generics,
+        quote! { ::serde::Serialize },
+    );
+    let mirror_enum = generate_mirror_enum(
+        type_name,
+        variant_names,
+        serde_attributes,
+        quote! { ::serde::Serialize },
+    );
+    let inner = match serialize_for {
+        SerializeFor::Enum => quote! { inner },
+        SerializeFor::Ref | SerializeFor::RefMut => quote! { &**inner },
+    };
+
+    quote! {
+        impl #impl_generics ::serde::Serialize for #impl_type_name #ty_generics #where_clause {
+            fn serialize<__S>(&self, serializer: __S) -> Result<__S::Ok, __S::Error>
+            where
+                __S: ::serde::Serializer,
+            {
+                #mirror_enum
+
+                let mirror: #type_name<#(&#struct_names #struct_ty_generics),*> = match self {
+                    #(
+                        Self::#variant_names(inner) => #type_name::#variant_names(#inner),
+                    )*
+                };
+                ::serde::Serialize::serialize(&mirror, serializer)
+            }
+        }
+    }
+}
+
+/// Implement `Deserialize` for the top-level enum by deserializing a mirror of it.
+pub fn generate_mirror_deserialize_impl(
+    type_name: &Ident,
+    generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+    serde_attributes: &SerdeAttributes,
+) -> proc_macro2::TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let mut impl_generics = generics.clone();
+    impl_generics.params.insert(0, parse_quote!('de));
+    let (impl_generics, _, _) = impl_generics.split_for_impl();
+    let where_clause = with_variant_bounds(
+        generics.where_clause.as_ref(),
+        struct_names,
+        &ty_generics,
+        quote! { ::serde::Deserialize<'de> },
+    );
+    let mirror_enum = generate_mirror_enum(
+        type_name,
+        variant_names,
+        serde_attributes,
+        quote! { ::serde::Deserialize },
+    );
+
+    quote! {
+        impl #impl_generics ::serde::Deserialize<'de> for #type_name #ty_generics #where_clause {
+            fn deserialize<__D>(deserializer: __D) -> Result<Self, __D::Error>
+            where
+                __D: ::serde::Deserializer<'de>,
+            {
+                #mirror_enum
+
+                let mirror = <#type_name<#(#struct_names #ty_generics),*> as ::serde::Deserialize>
+                    ::deserialize(deserializer)?;
+                Ok(match mirror {
+                    #(
+                        #type_name::#variant_names(inner) => Self::from(inner),
+                    )*
+                })
+            }
+        }
+    }
+}
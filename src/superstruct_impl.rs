@@ -0,0 +1,398 @@
+//! Generate variant-specific methods from an `impl` block on a superstruct, with dispatching
+//! methods on the top-level enum, `Ref` and `RefMut`.
+//!
+//! The `superstruct_impl` attribute doesn't know the variants of the superstruct, so it forwards
+//! the impl block to a helper macro generated alongside the superstruct, which adds the
+//! superstruct's configuration and passes everything on to `__superstruct_impl_expand`. The
+//! helper macro is only visible within the crate, as inherent impls can't be written elsewhere.
+//!
+//! Macros and types are in different namespaces, so the helper macro is re-exported under the
+//! name of the type. Wherever the type is in scope, so is its helper macro.
+use crate::attributes::IdentList;
+use crate::delegate::{get_receiver, make_turbofish, rename_args, Receiver};
+use crate::naming::generate_impl_helper_macro_name;
+use crate::utils::{contains_self_type, without_arguments, Boxing};
+use crate::ErrorOpts;
+use darling::{export::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    spanned::Spanned,
+    Error, FnArg, GenericArgument, Ident, ImplItem, ImplItemFn, ItemImpl, Lifetime, Path,
+    PathArguments, ReturnType, Type,
+};
+
+/// Configuration of the superstruct, passed on by its helper macro.
+#[derive(Debug, FromMeta)]
+pub struct ImplConfig {
+    pub variants: IdentList,
+    /// The variants which are stored in a `Box`.
+    #[darling(default)]
+    pub boxed: IdentList,
+    /// The variants which are boxed depending on their size.
+    #[darling(default)]
+    pub auto_boxed: IdentList,
+    #[darling(default)]
+    pub partial_getter_error: ErrorOpts,
+    /// Whether the superstruct has meta variants.
+    #[darling(default)]
+    pub meta: bool,
+}
+
+impl ImplConfig {
+    /// Whether the variant struct for `variant_name` is stored in a `Box`.
+    pub fn boxing(&self, variant_name: &Ident) -> Boxing {
+        if self.boxed.idents.contains(variant_name) {
+            Boxing::Boxed
+        } else if self.auto_boxed.idents.contains(variant_name) {
+            Boxing::Auto
+        } else {
+            Boxing::Unboxed
+        }
+    }
+}
+
+/// Options for the whole impl block, or for a single method via `#[superstruct(..)]`.
+#[derive(Debug, Default, FromMeta)]
+struct ImplOpts {
+    /// The variants to generate methods for, defaulting to all of them.
+    #[darling(default)]
+    only: Option<IdentList>,
+}
+
+/// The input of `__superstruct_impl_expand`: `[config] [args] impl ..`.
+pub struct ImplExpandInput {
+    config: TokenStream,
+    args: TokenStream,
+    item_impl: ItemImpl,
+}
+
+impl Parse for ImplExpandInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let config;
+        bracketed!(config in input);
+        let args;
+        bracketed!(args in input);
+        Ok(Self {
+            config: config.parse()?,
+            args: args.parse()?,
+            item_impl: input.parse()?,
+        })
+    }
+}
+
+/// Forward an impl block marked with `superstruct_impl` to the helper macro of its superstruct.
+///
+/// The helper macro has the same name as the type, so it is called using the path of the type in
+/// the impl block.
+pub fn superstruct_impl(args: TokenStream, item_impl: ItemImpl) -> syn::Result<TokenStream> {
+    let helper_macro_path = without_arguments(self_type_path(&item_impl)?);
+    Ok(quote! {
+        #helper_macro_path! { [#args] #item_impl }
+    })
+}
+
+/// Generate the helper macro for the superstruct `type_name`, used by `superstruct_impl` and
+/// `delegate`.
+///
+/// The helper macro calls `__superstruct_impl_expand` or `__superstruct_delegate_expand` at
+/// `crate_path`, which is the path of the `superstruct` crate.
+pub fn generate_impl_helper_macro(
+    type_name: &Ident,
+    variant_names: &[Ident],
+    boxed: &[Boxing],
+    partial_getter_error: &ErrorOpts,
+    is_meta: bool,
+    crate_path: &Path,
+) -> TokenStream {
+    let helper_macro_name = generate_impl_helper_macro_name(type_name);
+    let variant_names_boxed = |boxing| {
+        variant_names
+            .iter()
+            .zip(boxed)
+            .filter(move |(_, variant_boxing)| **variant_boxing == boxing)
+            .map(|(variant_name, _)| variant_name)
+    };
+    let boxed_variant_names = variant_names_boxed(Boxing::Boxed);
+    let auto_boxed_variant_names = variant_names_boxed(Boxing::Auto);
+    let error_ty = partial_getter_error.ty.iter();
+    let error_expr = partial_getter_error.expr.iter();
+    let config = quote! {
+        variants(#(#variant_names),*),
+        boxed(#(#boxed_variant_names),*),
+        auto_boxed(#(#auto_boxed_variant_names),*),
+        partial_getter_error(#(ty = #error_ty,)* #(expr = #error_expr)*),
+        meta = #is_meta,
+    };
+    let alias_names = [
+        type_name.clone(),
+        format_ident!("{}Ref", type_name),
+        format_ident!("{}RefMut", type_name),
+    ];
+    quote! {
+        macro_rules! #helper_macro_name {
+            (delegate $($item:tt)*) => {
+                #crate_path::__superstruct_delegate_expand! { [#config] $($item)* }
+            };
+            ([$($args:tt)*] $($item:tt)*) => {
+                #crate_path::__superstruct_impl_expand! { [#config] [$($args)*] $($item)* }
+            };
+        }
+        #(
+            #[allow(unused_imports)]
+            pub(crate) use #helper_macro_name as #alias_names;
+        )*
+    }
+}
+
+/// Generate the variant methods and dispatching methods for an impl block.
+pub fn superstruct_impl_expand(input: ImplExpandInput) -> syn::Result<TokenStream> {
+    let ImplExpandInput {
+        config,
+        args,
+        mut item_impl,
+    } = input;
+    let config = ImplConfig::from_list(&NestedMeta::parse_meta_list(config)?)?;
+    let block_opts = ImplOpts::from_list(&NestedMeta::parse_meta_list(args)?)?;
+    let all_variants = &config.variants.idents;
+    if let Some((_, trait_path, _)) = &item_impl.trait_ {
+        return Err(Error::new_spanned(
+            trait_path,
+            "`superstruct_impl` can't be used on a trait impl",
+        ));
+    }
+    if config.meta {
+        return Err(Error::new_spanned(
+            &item_impl.self_ty,
+            "`superstruct_impl` can't be used with meta variants",
+        ));
+    }
+
+    let self_path = self_type_path(&item_impl)?.clone();
+    let type_name = &self_path.segments.last().unwrap().ident;
+    let ref_path = with_type_name_suffix(&self_path, "Ref", true);
+    let ref_mut_path = with_type_name_suffix(&self_path, "RefMut", true);
+    let (err_ty, err_expr) = match config.partial_getter_error.parse() {
+        Some((err_ty, err_expr)) => (quote! { #err_ty }, quote! { #err_expr }),
+        None => (quote! { () }, quote! { () }),
+    };
+
+    let mut variant_methods = vec![vec![]; all_variants.len()];
+    let mut enum_items = vec![];
+    let mut ref_methods = vec![];
+    let mut ref_mut_methods = vec![];
+
+    for impl_item in std::mem::take(&mut item_impl.items) {
+        let ImplItem::Fn(mut method) = impl_item else {
+            // Constants and other items belong to the top-level enum.
+            enum_items.push(impl_item);
+            continue;
+        };
+
+        let method_opts = take_method_opts(&mut method)?;
+        let only = method_opts
+            .only
+            .or_else(|| block_opts.only.clone())
+            .map_or_else(|| all_variants.clone(), |only| only.idents);
+        if let Some(variant_name) = only
+            .iter()
+            .find(|variant_name| !all_variants.contains(variant_name))
+        {
+            return Err(Error::new(
+                variant_name.span(),
+                format!("`{variant_name}` is not a variant of `{type_name}`"),
+            ));
+        }
+        let is_total = all_variants.iter().all(|variant| only.contains(variant));
+
+        for (variant_name, methods) in all_variants.iter().zip(&mut variant_methods) {
+            if only.contains(variant_name) {
+                methods.push(method.clone());
+            }
+        }
+
+        // Methods which don't take `self`, or which use `Self` in their arguments or return
+        // type, only exist on the variant structs.
+        let Some(receiver) = get_receiver(&method.sig) else {
+            continue;
+        };
+        let returns_self =
+            matches!(&method.sig.output, ReturnType::Type(_, ty) if **ty == parse_quote!(Self));
+        let uses_self = method.sig.inputs.iter().any(
+            |input| matches!(input, FnArg::Typed(pat_type) if contains_self_type(&pat_type.ty)),
+        ) || matches!(
+            &method.sig.output,
+            ReturnType::Type(_, ty) if !returns_self && contains_self_type(ty)
+        );
+        if uses_self {
+            continue;
+        }
+
+        // Use the `self` token from the method, as the generated code is within a macro expansion.
+        let self_token = method.sig.receiver().unwrap().self_token;
+        let mut sig = method.sig.clone();
+        if let Some(FnArg::Receiver(receiver)) = sig
+            .inputs
+            .first_mut()
+            .filter(|_| receiver == Receiver::Value)
+        {
+            // A `mut self` receiver only matters to the body of the variant's method.
+            receiver.mutability = None;
+        }
+        let args = rename_args(&mut sig);
+        let turbofish = make_turbofish(&sig);
+        let fn_name = &sig.ident;
+        let call = quote! { inner.#fn_name #turbofish(#(#args),*) };
+        let ret_ty = match &sig.output {
+            ReturnType::Default => quote! { () },
+            ReturnType::Type(_, ty) => quote! { #ty },
+        };
+        if !is_total {
+            sig.output = parse_quote!(-> Result<#ret_ty, #err_ty>);
+        }
+
+        let make_dispatcher = |for_ref: bool| {
+            let arms = only.iter().map(|variant_name| {
+                let value = if returns_self && !for_ref {
+                    quote! { Self::from(#call) }
+                } else {
+                    call.clone()
+                };
+                if is_total {
+                    quote! { Self::#variant_name(inner) => #value, }
+                } else {
+                    quote! { Self::#variant_name(inner) => Ok(#value), }
+                }
+            });
+            let fallback = (!is_total).then(|| quote! { _ => Err(#err_expr), });
+            ImplItemFn {
+                attrs: method.attrs.clone(),
+                vis: method.vis.clone(),
+                defaultness: None,
+                sig: sig.clone(),
+                block: parse_quote!({
+                    match #self_token {
+                        #(#arms)*
+                        #fallback
+                    }
+                }),
+            }
+        };
+
+        enum_items.push(ImplItem::Fn(make_dispatcher(false)));
+        // The `Ref` and `RefMut` types only hold references to the variants, and `Self` is the
+        // reference type in their impl blocks.
+        if returns_self {
+            continue;
+        }
+        match receiver {
+            Receiver::Value => {}
+            Receiver::Ref => {
+                ref_methods.push(make_dispatcher(true));
+                ref_mut_methods.push(make_dispatcher(true));
+            }
+            Receiver::RefMut => ref_mut_methods.push(make_dispatcher(true)),
+        }
+    }
+
+    let attrs = &item_impl.attrs;
+    let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+    let mut ref_generics = item_impl.generics.clone();
+    ref_generics.params.insert(0, parse_quote!('__superstruct));
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+
+    let variant_impls = all_variants
+        .iter()
+        .zip(&variant_methods)
+        .filter(|(_, methods)| !methods.is_empty())
+        .map(|(variant_name, methods)| {
+            let variant_path = with_type_name_suffix(&self_path, &variant_name.to_string(), false);
+            quote! {
+                #(#attrs)*
+                impl #impl_generics #variant_path #where_clause {
+                    #(#methods)*
+                }
+            }
+        });
+    let ref_impl = (!ref_methods.is_empty()).then(|| {
+        quote! {
+            #(#attrs)*
+            impl #ref_impl_generics #ref_path #where_clause {
+                #(#ref_methods)*
+            }
+        }
+    });
+    let ref_mut_impl = (!ref_mut_methods.is_empty()).then(|| {
+        quote! {
+            #(#attrs)*
+            impl #ref_impl_generics #ref_mut_path #where_clause {
+                #(#ref_mut_methods)*
+            }
+        }
+    });
+
+    Ok(quote! {
+        #(#attrs)*
+        impl #impl_generics #self_path #where_clause {
+            #(#enum_items)*
+        }
+
+        #(#variant_impls)*
+
+        #ref_impl
+
+        #ref_mut_impl
+    })
+}
+
+/// Get the path of the type that an impl block is for.
+fn self_type_path(item_impl: &ItemImpl) -> syn::Result<&Path> {
+    match &*item_impl.self_ty {
+        Type::Path(type_path) if type_path.qself.is_none() => Ok(&type_path.path),
+        self_ty => Err(Error::new_spanned(
+            self_ty,
+            "`superstruct_impl` must be used on an impl for a superstruct",
+        )),
+    }
+}
+
+/// Append `suffix` to the type name at the end of `path`, keeping its generic arguments.
+///
+/// If `is_ref` is set then the `'__superstruct` lifetime is added as the first argument.
+fn with_type_name_suffix(path: &Path, suffix: &str, is_ref: bool) -> Path {
+    let mut path = path.clone();
+    let segment = path.segments.last_mut().unwrap();
+    segment.ident = format_ident!("{}{}", segment.ident, suffix);
+    if is_ref {
+        let lifetime: Lifetime = parse_quote!('__superstruct);
+        match &mut segment.arguments {
+            PathArguments::AngleBracketed(args) => {
+                args.args.insert(0, GenericArgument::Lifetime(lifetime));
+            }
+            arguments => *arguments = PathArguments::AngleBracketed(parse_quote!(<#lifetime>)),
+        }
+    }
+    path
+}
+
+/// Remove the `superstruct` attributes of a method, and parse its options from them.
+fn take_method_opts(method: &mut ImplItemFn) -> syn::Result<ImplOpts> {
+    let (superstruct_attrs, attrs) = std::mem::take(&mut method.attrs)
+        .into_iter()
+        .partition::<Vec<_>, _>(|attr| attr.path().is_ident("superstruct"));
+    method.attrs = attrs;
+    match superstruct_attrs.as_slice() {
+        [] => Ok(ImplOpts::default()),
+        [attr] => Ok(ImplOpts::from_meta(&attr.meta)?),
+        [_, attr, ..] => Err(Error::new(
+            attr.span(),
+            format!(
+                "more than one `superstruct` attribute on `{}`",
+                method.sig.ident
+            ),
+        )),
+    }
+}
@@ -1,7 +1,12 @@
+use crate::naming::generate_unbox_method_name;
 use proc_macro2::Span;
-use quote::quote;
+use quote::{quote, ToTokens};
 use smallvec::{smallvec, SmallVec};
-use syn::Token;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    parse_quote, GenericParam, Generics, Ident, Lifetime, Path, PathArguments, Token, Type,
+    TypePath, WhereClause, WherePredicate,
+};
 
 /// Convert an identifier from CamelCase to snake_case.
 pub fn snake_case(ident: &str) -> String {
@@ -23,11 +28,257 @@ pub fn snake_case(ident: &str) -> String {
         .collect()
 }
 
-/// Create a generics block like `<_, _, _>` with `num_generics` underscores.
-pub fn underscore_generics(num_generics: usize) -> proc_macro2::TokenStream {
+/// Create a generics block like `<'_, _, _>` with one placeholder per param of `generics`.
+///
+/// Lifetimes become `'_`, while type and const params become `_`.
+pub fn inferred_generics(generics: &Generics) -> proc_macro2::TokenStream {
     let underscore = Token![_](Span::call_site());
-    let underscores = std::iter::repeat_n(quote! { #underscore }, num_generics);
-    quote! { <#(#underscores),*> }
+    let placeholders = generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) => {
+            let lifetime = Lifetime::new("'_", Span::call_site());
+            quote! { #lifetime }
+        }
+        GenericParam::Type(_) | GenericParam::Const(_) => quote! { #underscore },
+    });
+    quote! { <#(#placeholders),*> }
+}
+
+/// Replace every occurrence of `Self` within a type by `replacement`.
+///
+/// Associated type paths like `Self::Item` are rewritten to `<Replacement>::Item`.
+///
+/// Return `true` if any occurrence of `Self` was replaced.
+pub fn replace_self_type(ty: &mut Type, replacement: &Type) -> bool {
+    struct ReplaceSelf<'a>(&'a Type, bool);
+
+    impl VisitMut for ReplaceSelf<'_> {
+        fn visit_type_mut(&mut self, ty: &mut Type) {
+            if let Type::Path(type_path) = ty {
+                let segments = &type_path.path.segments;
+                if type_path.qself.is_none()
+                    && type_path.path.leading_colon.is_none()
+                    && segments
+                        .first()
+                        .is_some_and(|segment| segment.ident == "Self")
+                {
+                    let replacement = self.0;
+                    self.1 = true;
+                    if segments.len() == 1 {
+                        *ty = replacement.clone();
+                        return;
+                    }
+                    let rest = segments.iter().skip(1);
+                    *ty = parse_quote!(<#replacement>::#(#rest)::*);
+                }
+            }
+            visit_mut::visit_type_mut(self, ty);
+        }
+    }
+
+    let mut visitor = ReplaceSelf(replacement, false);
+    visitor.visit_type_mut(ty);
+    visitor.1
+}
+
+/// Check whether a type mentions `Self` anywhere.
+pub fn contains_self_type(ty: &Type) -> bool {
+    replace_self_type(&mut ty.clone(), &parse_quote!(Self))
+}
+
+/// Check whether a type mentions `Self` other than in associated type paths like `Self::Item`.
+pub fn contains_bare_self_type(ty: &Type) -> bool {
+    struct FindSelf(bool);
+
+    impl VisitMut for FindSelf {
+        fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
+            if type_path.qself.is_none() && type_path.path.is_ident("Self") {
+                self.0 = true;
+            }
+            visit_mut::visit_type_path_mut(self, type_path);
+        }
+    }
+
+    let mut visitor = FindSelf(false);
+    visitor.visit_type_mut(&mut ty.clone());
+    visitor.0
+}
+
+/// Replace the last segment of `path` by `ident`, without any generic arguments.
+///
+/// This gives the path of an item defined in the same module as the item at `path`.
+pub fn with_last_segment(path: &Path, ident: Ident) -> Path {
+    let mut path = path.clone();
+    let segment = path
+        .segments
+        .last_mut()
+        .expect("path has at least one segment");
+    segment.ident = ident;
+    segment.arguments = PathArguments::None;
+    path
+}
+
+/// Remove the generic arguments from the last segment of `path`.
+///
+/// This gives the path of a macro with the same name as the type or trait at `path`.
+pub fn without_arguments(path: &Path) -> Path {
+    let mut path = path.clone();
+    path.segments
+        .last_mut()
+        .expect("path has at least one segment")
+        .arguments = PathArguments::None;
+    path
+}
+
+/// Find the type and lifetime parameters of `generics` which aren't mentioned in any of `tys`.
+///
+/// Const parameters are never returned, as the compiler doesn't require them to be used.
+pub fn unused_generic_params<'a, 'b>(
+    generics: &'a Generics,
+    tys: impl IntoIterator<Item = &'b Type>,
+) -> Vec<&'a GenericParam> {
+    #[derive(Default)]
+    struct CollectNames {
+        idents: Vec<Ident>,
+        lifetimes: Vec<Lifetime>,
+    }
+
+    impl VisitMut for CollectNames {
+        fn visit_type_path_mut(&mut self, type_path: &mut TypePath) {
+            if let Some(segment) = type_path.path.segments.first() {
+                self.idents.push(segment.ident.clone());
+            }
+            visit_mut::visit_type_path_mut(self, type_path);
+        }
+
+        fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+            self.lifetimes.push(lifetime.clone());
+        }
+    }
+
+    let mut names = CollectNames::default();
+    for ty in tys {
+        names.visit_type_mut(&mut ty.clone());
+    }
+
+    generics
+        .params
+        .iter()
+        .filter(|param| match param {
+            GenericParam::Type(type_param) => !names.idents.contains(&type_param.ident),
+            GenericParam::Lifetime(lifetime_param) => {
+                !names.lifetimes.contains(&lifetime_param.lifetime)
+            }
+            GenericParam::Const(_) => false,
+        })
+        .collect()
+}
+
+/// Create a type parameter name starting with `name` which doesn't clash with `generics`.
+///
+/// Underscores are appended to `name` until it is unique.
+pub fn fresh_type_param(generics: &Generics, name: &str) -> Ident {
+    let mut name = name.to_string();
+    while generics.type_params().any(|param| param.ident == name)
+        || generics.const_params().any(|param| param.ident == name)
+    {
+        name.push('_');
+    }
+    Ident::new(&name, Span::call_site())
+}
+
+/// How a variant struct is stored in its enum variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boxing {
+    Unboxed,
+    Boxed,
+    /// Boxed if the variant struct is larger than `box_variants_larger_than`, which only the
+    /// compiler knows. The variant struct is moved or borrowed out using the hidden methods of the
+    /// enum named by `generate_unbox_method_name`.
+    Auto,
+}
+
+/// Move a variant struct out of `binding`, unboxing it if it is stored in a `Box`.
+///
+/// `type_path` is the path of the enum, used to call its unboxing method for `Boxing::Auto`.
+pub fn unboxed(
+    binding: impl ToTokens,
+    boxing: Boxing,
+    type_path: impl ToTokens,
+    variant_name: &Ident,
+) -> proc_macro2::TokenStream {
+    match boxing {
+        Boxing::Unboxed => quote! { #binding },
+        Boxing::Boxed => quote! { *#binding },
+        Boxing::Auto => {
+            let unbox_method_name = generate_unbox_method_name(variant_name, None);
+            quote! { #type_path::#unbox_method_name(#binding) }
+        }
+    }
+}
+
+/// Borrow a variant struct from `binding`, which is a reference to the enum variant's field.
+pub fn unboxed_ref(
+    binding: impl ToTokens,
+    boxing: Boxing,
+    type_path: impl ToTokens,
+    variant_name: &Ident,
+    mutable: bool,
+) -> proc_macro2::TokenStream {
+    match boxing {
+        Boxing::Unboxed => quote! { #binding },
+        Boxing::Boxed if mutable => quote! { &mut **#binding },
+        Boxing::Boxed => quote! { &**#binding },
+        Boxing::Auto => {
+            let unbox_method_name = generate_unbox_method_name(variant_name, Some(mutable));
+            quote! { #type_path::#unbox_method_name(#binding) }
+        }
+    }
+}
+
+/// Extend `where_clause` with a bound of `bound` for each variant struct.
+///
+/// The result always has a `where` token and a trailing comma, so more predicates may follow it.
+pub fn with_variant_bounds(
+    where_clause: Option<&WhereClause>,
+    struct_names: &[impl ToTokens],
+    struct_ty_generics: &impl ToTokens,
+    bound: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let predicates = where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter());
+    quote! {
+        where
+            #(#predicates,)*
+            #(#struct_names #struct_ty_generics: #bound,)*
+    }
+}
+
+/// Get a bound `T: 'a` for every type param `T` and lifetime param `'a` of `generics`.
+///
+/// A struct with a field like `&'a T` implies that `T: 'a`, but a trait mentioning the struct in
+/// its supertraits can't rely on this, so the bounds must be given explicitly.
+pub fn outlives_predicates(generics: &Generics) -> Vec<WherePredicate> {
+    generics
+        .type_params()
+        .flat_map(|type_param| {
+            let ident = &type_param.ident;
+            generics.lifetimes().map(move |lifetime_param| {
+                let lifetime = &lifetime_param.lifetime;
+                parse_quote!(#ident: #lifetime)
+            })
+        })
+        .collect()
+}
+
+/// Extend the where clause of `generics` with the bounds from `outlives_predicates`.
+pub fn with_outlives_bounds(generics: &Generics) -> Generics {
+    let predicates = outlives_predicates(generics);
+    let mut generics = generics.clone();
+    if !predicates.is_empty() {
+        generics.make_where_clause().predicates.extend(predicates);
+    }
+    generics
 }
 
 #[cfg(test)]
@@ -42,4 +293,79 @@ mod test {
         assert_eq!(snake_case("hello_world"), "hello_world");
         assert_eq!(snake_case("__"), "__");
     }
+
+    #[test]
+    fn inferred_generics_correct() {
+        let generics: Generics = parse_quote!(<'a, T: Clone, const N: usize>);
+        assert_eq!(
+            inferred_generics(&generics).to_string(),
+            quote!(<'_, _, _>).to_string()
+        );
+        assert_eq!(
+            inferred_generics(&Generics::default()).to_string(),
+            quote!(<>).to_string()
+        );
+    }
+
+    #[test]
+    fn replace_self_type_correct() {
+        let replacement: Type = parse_quote!(Foo<T>);
+        let check = |mut ty: Type, expected: Type| {
+            replace_self_type(&mut ty, &replacement);
+            assert_eq!(quote!(#ty).to_string(), quote!(#expected).to_string());
+        };
+        check(parse_quote!(Self), parse_quote!(Foo<T>));
+        check(
+            parse_quote!(Option<Box<Self>>),
+            parse_quote!(Option<Box<Foo<T>>>),
+        );
+        check(
+            parse_quote!(Vec<(Self, u8)>),
+            parse_quote!(Vec<(Foo<T>, u8)>),
+        );
+        check(parse_quote!(Self::Item), parse_quote!(<Foo<T>>::Item));
+        check(
+            parse_quote!(HashMap<u8, Self>),
+            parse_quote!(HashMap<u8, Foo<T>>),
+        );
+        check(parse_quote!(u64), parse_quote!(u64));
+
+        let mut ty: Type = parse_quote!(Vec<u8>);
+        assert!(!replace_self_type(&mut ty, &replacement));
+    }
+
+    #[test]
+    fn unused_generic_params_correct() {
+        let generics: Generics = parse_quote!(<'a, 'b, T, U: Clone, V, const N: usize>);
+        let tys: Vec<Type> = vec![
+            parse_quote!(&'a str),
+            parse_quote!(Vec<T>),
+            parse_quote!(<U as Clone>::Target),
+        ];
+        let unused = unused_generic_params(&generics, &tys)
+            .into_iter()
+            .map(|param| quote!(#param).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(unused, vec!["'b", "V"]);
+    }
+
+    #[test]
+    fn fresh_type_param_unique() {
+        let generics: Generics = parse_quote!(<'a, V, const V_: usize>);
+        assert_eq!(fresh_type_param(&generics, "V"), "V__");
+        assert_eq!(fresh_type_param(&generics, "F"), "F");
+        assert_eq!(fresh_type_param(&Generics::default(), "V"), "V");
+    }
+
+    #[test]
+    fn outlives_predicates_correct() {
+        let generics: Generics = parse_quote!(<'a, 'b, T, const N: usize, U: Clone>);
+        let predicates = outlives_predicates(&generics)
+            .into_iter()
+            .map(|predicate| quote!(#predicate).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(predicates, ["T : 'a", "T : 'b", "U : 'a", "U : 'b"]);
+        assert!(outlives_predicates(&parse_quote!(<T, U>)).is_empty());
+        assert!(outlives_predicates(&parse_quote!(<'a>)).is_empty());
+    }
 }
@@ -0,0 +1,192 @@
+//! Generate the `FooKind` enum and the trait implemented by every variant struct of a superstruct.
+use crate::naming::{generate_kind_enum_name, generate_variant_trait_name};
+use crate::utils::{fresh_type_param, outlives_predicates, unboxed, with_outlives_bounds, Boxing};
+use quote::quote;
+use syn::{Generics, Ident, Lifetime, Visibility};
+
+/// Generate the fieldless `FooKind` enum, with one variant per variant of the top-level enum.
+pub fn generate_kind_enum(
+    type_name: &Ident,
+    visibility: &Visibility,
+    variant_names: &[Ident],
+) -> proc_macro2::TokenStream {
+    let kind_name = generate_kind_enum_name(type_name);
+    let doc = format!("The kinds of variant of [`{type_name}`].");
+    let num_variants = variant_names.len();
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #visibility enum #kind_name {
+            #(
+                #variant_names,
+            )*
+        }
+
+        impl #kind_name {
+            /// All kinds, in the order that the variants are declared.
+            pub const ALL: [Self; #num_variants] = [#(Self::#variant_names),*];
+        }
+    }
+}
+
+/// Generate the `variant_kind` method for the top-level enum, `Ref` or `RefMut`.
+pub fn generate_variant_kind_method(
+    type_name: &Ident,
+    variant_names: &[Ident],
+) -> proc_macro2::TokenStream {
+    let kind_name = generate_kind_enum_name(type_name);
+    quote! {
+        pub fn variant_kind(&self) -> #kind_name {
+            match self {
+                #(
+                    Self::#variant_names(_) => #kind_name::#variant_names,
+                )*
+            }
+        }
+    }
+}
+
+/// Generate the `FooVariant` trait and implement it for each variant struct.
+///
+/// The `ref_generics` are the generics of the `Ref` and `RefMut` types, which begin with
+/// `ref_lifetime`.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_variant_trait(
+    type_name: &Ident,
+    ref_type_name: &Ident,
+    ref_mut_type_name: &Ident,
+    visibility: &Visibility,
+    generics: &Generics,
+    ref_generics: &Generics,
+    ref_lifetime: &Lifetime,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+    boxed: &[Boxing],
+) -> proc_macro2::TokenStream {
+    let trait_name = generate_variant_trait_name(type_name);
+    let kind_name = generate_kind_enum_name(type_name);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // The supertrait `Into<Foo>` requires the implied bounds of `Foo` to be given explicitly.
+    let outlives = outlives_predicates(generics);
+    let trait_generics = with_outlives_bounds(generics);
+    let trait_where_clause = &trait_generics.where_clause;
+    let (ref_impl_generics, ref_ty_generics, _) = ref_generics.split_for_impl();
+    let variant_param = fresh_type_param(generics, "V");
+    let doc = format!("A variant struct of [`{type_name}`].");
+    let inners = variant_names
+        .iter()
+        .zip(boxed)
+        .map(|(variant_name, boxing)| unboxed(quote! { inner }, *boxing, type_name, variant_name));
+
+    quote! {
+        #[doc = #doc]
+        #visibility trait #trait_name #generics: Sized + Into<#type_name #ty_generics>
+        #trait_where_clause
+        {
+            /// The kind of this variant.
+            const KIND: #kind_name;
+
+            /// Convert this variant into the top-level enum.
+            fn into_enum(self) -> #type_name #ty_generics;
+
+            /// Extract this variant from the top-level enum, or return the enum if it is a
+            /// different variant.
+            fn from_enum(value: #type_name #ty_generics) -> Result<Self, #type_name #ty_generics>;
+
+            /// Extract a reference to this variant, if the `Ref` is this variant.
+            fn from_ref<#ref_lifetime>(
+                value: #ref_type_name #ref_ty_generics,
+            ) -> Option<&#ref_lifetime Self>;
+
+            /// Extract a mutable reference to this variant, if the `RefMut` is this variant.
+            fn from_ref_mut<#ref_lifetime>(
+                value: #ref_mut_type_name #ref_ty_generics,
+            ) -> Option<&#ref_lifetime mut Self>;
+        }
+
+        #(
+            impl #impl_generics #trait_name #ty_generics for #struct_names #ty_generics
+            #trait_where_clause
+            {
+                const KIND: #kind_name = #kind_name::#variant_names;
+
+                fn into_enum(self) -> #type_name #ty_generics {
+                    self.into()
+                }
+
+                fn from_enum(value: #type_name #ty_generics) -> Result<Self, #type_name #ty_generics> {
+                    match value {
+                        #type_name::#variant_names(inner) => Ok(#inners),
+                        #[allow(unreachable_patterns)]
+                        other => Err(other),
+                    }
+                }
+
+                fn from_ref<#ref_lifetime>(
+                    value: #ref_type_name #ref_ty_generics,
+                ) -> Option<&#ref_lifetime Self> {
+                    match value {
+                        #ref_type_name::#variant_names(inner) => Some(inner),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
+
+                fn from_ref_mut<#ref_lifetime>(
+                    value: #ref_mut_type_name #ref_ty_generics,
+                ) -> Option<&#ref_lifetime mut Self> {
+                    match value {
+                        #ref_mut_type_name::#variant_names(inner) => Some(inner),
+                        #[allow(unreachable_patterns)]
+                        _ => None,
+                    }
+                }
+            }
+        )*
+
+        impl #impl_generics #type_name #ty_generics #where_clause {
+            /// Get a reference to the variant struct of type `V`, if this is that variant.
+            pub fn try_get<#variant_param: #trait_name #ty_generics>(&self) -> Option<&#variant_param>
+            where
+                #(#outlives,)*
+            {
+                #variant_param::from_ref(self.to_ref())
+            }
+
+            /// Get a mutable reference to the variant struct of type `V`, if this is that variant.
+            pub fn try_get_mut<#variant_param: #trait_name #ty_generics>(
+                &mut self,
+            ) -> Option<&mut #variant_param>
+            where
+                #(#outlives,)*
+            {
+                #variant_param::from_ref_mut(self.to_mut())
+            }
+        }
+
+        impl #ref_impl_generics #ref_type_name #ref_ty_generics #where_clause {
+            /// Get a reference to the variant struct of type `V`, if this is that variant.
+            pub fn try_get<#variant_param: #trait_name #ty_generics>(
+                self,
+            ) -> Option<&#ref_lifetime #variant_param>
+            where
+                #(#outlives,)*
+            {
+                #variant_param::from_ref(self)
+            }
+        }
+
+        impl #ref_impl_generics #ref_mut_type_name #ref_ty_generics #where_clause {
+            /// Get a mutable reference to the variant struct of type `V`, if this is that variant.
+            pub fn try_get_mut<#variant_param: #trait_name #ty_generics>(
+                self,
+            ) -> Option<&#ref_lifetime mut #variant_param>
+            where
+                #(#outlives,)*
+            {
+                #variant_param::from_ref_mut(self)
+            }
+        }
+    }
+}
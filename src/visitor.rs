@@ -0,0 +1,217 @@
+//! Generate visitor traits, which are a macro-free alternative to the map macros.
+use crate::naming::{generate_variant_trait_name, generate_visitor_trait_names};
+use crate::utils::{fresh_type_param, snake_case, unboxed, with_outlives_bounds, Boxing};
+use itertools::Itertools;
+use quote::{format_ident, quote};
+use syn::{Generics, Ident, Visibility};
+
+/// Generate the `FooVisitor`, `FooVisitorMut` and `FooFolder` traits.
+pub fn generate_visitor_traits(
+    type_name: &Ident,
+    visibility: &Visibility,
+    generics: &Generics,
+    variant_names: &[Ident],
+    struct_names: &[Ident],
+) -> proc_macro2::TokenStream {
+    let variant_trait_name = generate_variant_trait_name(type_name);
+    let (visitor_name, visitor_mut_name, folder_name) = generate_visitor_trait_names(type_name);
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let variant_param = fresh_type_param(generics, "V");
+
+    // The variant trait requires the implied bounds of the top-level enum to be given explicitly.
+    let trait_generics = with_outlives_bounds(generics);
+    let where_clause = &trait_generics.where_clause;
+
+    let visit_fns = visit_fn_names(variant_names, "visit_", "");
+    let visit_mut_fns = visit_fn_names(variant_names, "visit_", "_mut");
+    let fold_fns = visit_fn_names(variant_names, "fold_", "");
+
+    let visitor_doc = format!(
+        "Visitor for references to the variants of [`{type_name}`], used by its `accept` methods.\n\n\
+         Each variant's method defaults to calling `visit_any`."
+    );
+    let visitor_mut_doc = format!(
+        "Visitor for mutable references to the variants of [`{type_name}`], used by its \
+         `accept_mut` methods.\n\n\
+         Each variant's method defaults to calling `visit_any_mut`."
+    );
+    let folder_doc = format!(
+        "Folder which consumes the variants of [`{type_name}`], used by its `fold` method.\n\n\
+         Each variant's method defaults to calling `fold_any`."
+    );
+
+    quote! {
+        #[doc = #visitor_doc]
+        #visibility trait #visitor_name #generics #where_clause {
+            type Output;
+
+            fn visit_any<#variant_param: #variant_trait_name #ty_generics>(
+                &mut self,
+                variant: &#variant_param,
+            ) -> Self::Output;
+
+            #(
+                fn #visit_fns(&mut self, variant: &#struct_names #ty_generics) -> Self::Output {
+                    self.visit_any(variant)
+                }
+            )*
+        }
+
+        #[doc = #visitor_mut_doc]
+        #visibility trait #visitor_mut_name #generics #where_clause {
+            type Output;
+
+            fn visit_any_mut<#variant_param: #variant_trait_name #ty_generics>(
+                &mut self,
+                variant: &mut #variant_param,
+            ) -> Self::Output;
+
+            #(
+                fn #visit_mut_fns(&mut self, variant: &mut #struct_names #ty_generics) -> Self::Output {
+                    self.visit_any_mut(variant)
+                }
+            )*
+        }
+
+        #[doc = #folder_doc]
+        #visibility trait #folder_name #generics #where_clause {
+            type Output;
+
+            fn fold_any<#variant_param: #variant_trait_name #ty_generics>(
+                &mut self,
+                variant: #variant_param,
+            ) -> Self::Output;
+
+            #(
+                fn #fold_fns(&mut self, variant: #struct_names #ty_generics) -> Self::Output {
+                    self.fold_any(variant)
+                }
+            )*
+        }
+    }
+}
+
+/// Generate the `accept` and `fold` methods for the top-level enum.
+pub fn generate_enum_accept_methods(
+    type_name: &Ident,
+    generics: &Generics,
+    variant_names: &[Ident],
+    boxed: &[Boxing],
+) -> proc_macro2::TokenStream {
+    let (visitor_name, visitor_mut_name, folder_name) = generate_visitor_trait_names(type_name);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let visitor_param = fresh_type_param(generics, "Vis");
+    let folder_param = fresh_type_param(generics, "F");
+
+    let visit_fns = visit_fn_names(variant_names, "visit_", "");
+    let visit_mut_fns = visit_fn_names(variant_names, "visit_", "_mut");
+    let fold_fns = visit_fn_names(variant_names, "fold_", "");
+    let inners = variant_names
+        .iter()
+        .zip(boxed)
+        .map(|(variant_name, boxing)| unboxed(quote! { inner }, *boxing, type_name, variant_name));
+
+    quote! {
+        impl #impl_generics #type_name #ty_generics #where_clause {
+            pub fn accept<#visitor_param: #visitor_name #ty_generics>(
+                &self,
+                visitor: &mut #visitor_param,
+            ) -> #visitor_param::Output {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #visitor_param::#visit_fns(visitor, inner),
+                    )*
+                }
+            }
+
+            pub fn accept_mut<#visitor_param: #visitor_mut_name #ty_generics>(
+                &mut self,
+                visitor: &mut #visitor_param,
+            ) -> #visitor_param::Output {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #visitor_param::#visit_mut_fns(visitor, inner),
+                    )*
+                }
+            }
+
+            pub fn fold<#folder_param: #folder_name #ty_generics>(
+                self,
+                folder: &mut #folder_param,
+            ) -> #folder_param::Output {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #folder_param::#fold_fns(folder, #inners),
+                    )*
+                }
+            }
+        }
+    }
+}
+
+/// Generate the `accept` method (and `accept_mut` if `mutable`) for a `Ref` or `RefMut` type.
+///
+/// The `ref_generics` are the generics of the `Ref` type, while `generics` are the generics of
+/// the top-level enum, which are used for the visitor traits.
+pub fn generate_ref_accept_methods(
+    type_name: &Ident,
+    ref_type_name: &Ident,
+    generics: &Generics,
+    ref_generics: &Generics,
+    variant_names: &[Ident],
+    mutable: bool,
+) -> proc_macro2::TokenStream {
+    let (visitor_name, visitor_mut_name, _) = generate_visitor_trait_names(type_name);
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let (ref_impl_generics, ref_ty_generics, where_clause) = ref_generics.split_for_impl();
+    let visitor_param = fresh_type_param(generics, "Vis");
+
+    let visit_fns = visit_fn_names(variant_names, "visit_", "");
+    let visit_mut_fns = visit_fn_names(variant_names, "visit_", "_mut");
+
+    let accept_mut = mutable.then(|| {
+        quote! {
+            pub fn accept_mut<#visitor_param: #visitor_mut_name #ty_generics>(
+                &mut self,
+                visitor: &mut #visitor_param,
+            ) -> #visitor_param::Output {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #visitor_param::#visit_mut_fns(visitor, inner),
+                    )*
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl #ref_impl_generics #ref_type_name #ref_ty_generics #where_clause {
+            pub fn accept<#visitor_param: #visitor_name #ty_generics>(
+                &self,
+                visitor: &mut #visitor_param,
+            ) -> #visitor_param::Output {
+                match self {
+                    #(
+                        Self::#variant_names(inner) => #visitor_param::#visit_fns(visitor, inner),
+                    )*
+                }
+            }
+
+            #accept_mut
+        }
+    }
+}
+
+fn visit_fn_names(variant_names: &[Ident], prefix: &str, suffix: &str) -> Vec<Ident> {
+    variant_names
+        .iter()
+        .map(|variant_name| {
+            format_ident!(
+                "{}{}{}",
+                prefix,
+                snake_case(&variant_name.to_string()),
+                suffix
+            )
+        })
+        .collect_vec()
+}
@@ -0,0 +1,132 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[superstruct(variants(Base, Ext, Full))]
+pub struct Block {
+    #[superstruct(accessor_trait = "HasSlot")]
+    slot: u64,
+    #[superstruct(getter(copy, no_mut), accessor_trait = "HasSlot")]
+    epoch: u64,
+    #[superstruct(only(Ext, Full), accessor_trait = "HasExtra")]
+    extra: String,
+    #[superstruct(
+        only(Full),
+        partial_getter(rename = "full_data", no_mut),
+        accessor_trait = "HasData"
+    )]
+    data: Vec<u8>,
+}
+
+#[test]
+fn accessor_trait_common() {
+    fn bump<T: HasSlotMut>(value: &mut T) -> u64 {
+        *value.slot_mut() += 1;
+        *value.slot() + value.epoch()
+    }
+
+    fn total<T: HasSlot>(value: &T) -> u64 {
+        *value.slot() + value.epoch()
+    }
+
+    let mut base = BlockBase { slot: 1, epoch: 10 };
+    assert_eq!(bump(&mut base), 12);
+
+    let mut block = Block::Base(base);
+    assert_eq!(bump(&mut block), 13);
+    assert_eq!(bump(&mut block.to_mut()), 14);
+    assert_eq!(HasSlot::slot(&block.to_mut()), &4);
+    // `Ref` implements the read-only trait, but not the `_mut` subtrait.
+    assert_eq!(total(&block.to_ref()), 14);
+}
+
+#[test]
+fn accessor_trait_partial() {
+    fn describe<T: HasExtra>(value: &T) -> Result<String, ()> {
+        value.extra().cloned()
+    }
+
+    fn data_len<T: HasData>(value: &T) -> Result<usize, ()> {
+        value.full_data().map(Vec::len)
+    }
+
+    let mut ext = BlockExt {
+        slot: 1,
+        epoch: 2,
+        extra: "ext".into(),
+    };
+    ext.extra_mut().unwrap().push('!');
+    assert_eq!(describe(&ext), Ok("ext!".into()));
+
+    let full = BlockFull {
+        slot: 1,
+        epoch: 2,
+        extra: "full".into(),
+        data: vec![1, 2, 3],
+    };
+    assert_eq!(describe(&full), Ok("full".into()));
+    assert_eq!(data_len(&full), Ok(3));
+
+    // The enums implement the partial traits, returning an error for other variants.
+    let mut block = Block::Ext(ext);
+    HasExtraMut::extra_mut(&mut block.to_mut())
+        .unwrap()
+        .push('?');
+    assert_eq!(describe(&block), Ok("ext!?".into()));
+    assert_eq!(describe(&block.to_ref()), Ok("ext!?".into()));
+    assert_eq!(data_len(&block), Err(()));
+    assert_eq!(data_len(&Block::Full(full).to_ref()), Ok(3));
+}
+
+#[test]
+fn accessor_trait_ref() {
+    #[superstruct(variants(A, B))]
+    pub struct Item<T> {
+        #[superstruct(getter(no_mut), accessor_trait = "HasValue")]
+        value: T,
+    }
+
+    fn get<V: HasValue<u8>>(item: &V) -> u8 {
+        *item.value()
+    }
+
+    let item = Item::B(ItemB { value: 7u8 });
+    assert_eq!(get(&item), 7);
+    assert_eq!(get(&item.to_ref()), 7);
+    assert_eq!(get(&ItemA { value: 1 }), 1);
+}
+
+#[test]
+fn accessor_trait_meta_variants() {
+    #[superstruct(meta_variants(Read, Write), variants(Lower, Upper))]
+    pub struct Message {
+        #[superstruct(accessor_trait = "HasId")]
+        id: u64,
+        #[superstruct(meta_only(Write), accessor_trait = "HasText")]
+        text: String,
+    }
+
+    fn bump<T: HasIdMut>(value: &mut T) -> u64 {
+        *value.id_mut() += 1;
+        *value.id()
+    }
+
+    let mut message = Message::Write(MessageWrite::Upper(MessageWriteUpper {
+        id: 1,
+        text: "hello".into(),
+    }));
+    assert_eq!(bump(&mut message), 2);
+    assert_eq!(bump(&mut message.to_mut()), 3);
+    assert_eq!(bump(message.as_write_mut().unwrap()), 4);
+
+    let upper = MessageWriteUpper {
+        id: 0,
+        text: "upper".into(),
+    };
+    assert_eq!(HasText::text(&upper), Ok(&"upper".into()));
+    assert_eq!(HasText::text(&message), Ok(&"hello".into()));
+
+    let read = Message::Read(MessageRead::Lower(MessageReadLower { id: 5 }));
+    assert_eq!(HasText::text(&read), Err(()));
+    assert_eq!(HasText::text(&read.to_ref()), Err(()));
+}
@@ -69,7 +69,7 @@ fn basic() {
 
 // Test that superstruct's enum ordering is based on the ordering in `variants(...)`.
 // This test fails with variant order (A, B) because A is a subset of B and we're not
-// using `serde(deny_unknown_fields)`.
+// using `serde(deny_unknown_fields)`. See `untagged_deserialize` for an alternative.
 #[test]
 fn serde_deserialise_order() {
     #[superstruct(
@@ -0,0 +1,351 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use std::mem::size_of;
+use superstruct::{delegate, superstruct};
+
+#[superstruct(
+    variants(Small, Large),
+    variant_attributes(derive(Debug, Clone, PartialEq)),
+    boxed(Large),
+    cow,
+    ref_cloned,
+    ref_traits(Debug, PartialEq),
+    variant_trait,
+    map_into(Summary)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    slot: u64,
+    #[superstruct(only(Large))]
+    data: [u8; 128],
+}
+
+#[superstruct(
+    variants(Small, Large, Huge),
+    variant_attributes(derive(Debug, PartialEq)),
+    boxed
+)]
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    slot: u64,
+}
+
+impl BlockSmall {
+    fn into_summary(self) -> SummarySmall {
+        SummarySmall { slot: self.slot }
+    }
+}
+
+impl BlockLarge {
+    fn into_summary(self) -> SummaryLarge {
+        SummaryLarge { slot: self.slot }
+    }
+}
+
+#[test]
+fn boxed_variant() {
+    assert!(size_of::<Block>() < 64);
+
+    let mut large = Block::from(BlockLarge {
+        slot: 1,
+        data: [7; 128],
+    });
+    assert!(matches!(large, Block::Large(ref inner) if inner.data[0] == 7));
+    assert_eq!(*large.slot(), 1);
+    assert_eq!(large.data().map(|data| data[1]), Ok(7));
+    *large.slot_mut() = 2;
+    assert_eq!(large.as_large().map(|inner| inner.slot), Ok(2));
+    assert!(large.as_small().is_err());
+    large.as_large_mut().unwrap().data[0] = 8;
+    assert_eq!(large.to_ref().data().map(|data| data[0]), Ok(8));
+    *large.to_mut().slot_mut() = 3;
+    assert_eq!(*large.to_ref().slot(), 3);
+
+    assert_eq!(large.to_ref().cloned(), large);
+    assert_eq!(BlockCow::from(&large).into_owned(), large);
+    assert_eq!(Block::from(BlockCow::from(large.clone())), large);
+    assert!(large.to_ref() == large);
+    let large_inner = BlockLarge::from_enum(large.clone()).unwrap();
+    assert_eq!(large_inner.data[..2], [8, 7]);
+    assert!(large == large_inner);
+    assert_eq!(large_inner.clone().into_enum(), large);
+    assert_eq!(large.try_get::<BlockLarge>(), Some(&large_inner));
+
+    let small = Block::Small(BlockSmall { slot: 4 });
+    for block in [small, large] {
+        let slot = *block.slot();
+        let summary = map_block_into_summary!(block, |inner, cons| cons(inner.into_summary()));
+        assert_eq!(*summary.slot(), slot);
+    }
+}
+
+#[test]
+fn boxed_map_pair() {
+    fn merge(a: Block, b: Block) -> Result<Block, (Block, Block)> {
+        map_block_pair!(
+            a,
+            b,
+            |mut inner_a, inner_b, cons| {
+                inner_a.slot += inner_b.slot;
+                Ok(cons(inner_a))
+            },
+            |a, b| Err((a, b))
+        )
+    }
+
+    let large = |slot| {
+        Block::Large(Box::new(BlockLarge {
+            slot,
+            data: [0; 128],
+        }))
+    };
+    assert_eq!(merge(large(1), large(2)), Ok(large(3)));
+    let small = Block::Small(BlockSmall { slot: 1 });
+    assert!(merge(small.clone(), large(2)).is_err());
+    assert_eq!(
+        merge(small.clone(), small),
+        Ok(Block::Small(BlockSmall { slot: 2 }))
+    );
+}
+
+#[test]
+fn boxed_all_meta() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(Base, Altair),
+        variant_attributes(derive(Debug, Clone, PartialEq)),
+        boxed,
+        variant_trait,
+        no_map_macros
+    )]
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Payload {
+        slot: u64,
+        #[superstruct(meta_only(Full))]
+        transactions: Vec<u8>,
+    }
+
+    assert_eq!(size_of::<PayloadFull>(), size_of::<Box<()>>() * 2);
+
+    let mut payload = Payload::Full(PayloadFull::from(PayloadFullAltair {
+        slot: 1,
+        transactions: vec![2],
+    }));
+    assert!(matches!(payload, Payload::Full(PayloadFull::Altair(_))));
+    assert_eq!(*payload.slot(), 1);
+    assert_eq!(payload.transactions(), Ok(&vec![2]));
+    *payload.slot_mut() = 5;
+    assert_eq!(*payload.to_ref().slot(), 5);
+
+    let blinded = PayloadBlinded::from(PayloadBlindedBase { slot: 6 });
+    let PayloadBlinded::Base(inner) = blinded.clone() else {
+        panic!("expected a base payload");
+    };
+    assert_eq!(inner.slot, 6);
+    assert_eq!(PayloadBlindedBase::from_enum(blinded.clone()), Ok(*inner));
+    assert_eq!(
+        PayloadBlindedAltair::from_enum(blinded.clone()),
+        Err(blinded)
+    );
+}
+
+#[superstruct(
+    variants(Small, Large, Huge),
+    variant_attributes(derive(Debug, Clone, PartialEq)),
+    boxed(Huge),
+    box_variants_larger_than = 64,
+    cow,
+    ref_cloned,
+    ref_traits(Debug, PartialEq),
+    variant_trait,
+    visitors,
+    map_into(Summary)
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    #[superstruct(getter(copy))]
+    slot: u64,
+    #[superstruct(only(Large), partial_getter(copy))]
+    data: [u8; 128],
+    #[superstruct(only(Huge))]
+    extra: [u8; 32],
+}
+
+impl PacketSmall {
+    fn into_summary(self) -> SummarySmall {
+        SummarySmall { slot: self.slot }
+    }
+}
+
+impl PacketLarge {
+    fn into_summary(self) -> SummaryLarge {
+        SummaryLarge { slot: self.slot }
+    }
+}
+
+impl PacketHuge {
+    fn into_summary(self) -> SummaryHuge {
+        SummaryHuge { slot: self.slot }
+    }
+}
+
+pub trait Weigh {
+    fn weight(&self) -> usize;
+    fn bump(&mut self);
+    fn into_slot(self) -> u64;
+}
+
+impl Weigh for PacketSmall {
+    fn weight(&self) -> usize {
+        1
+    }
+    fn bump(&mut self) {
+        self.slot += 1;
+    }
+    fn into_slot(self) -> u64 {
+        self.slot
+    }
+}
+
+impl Weigh for PacketLarge {
+    fn weight(&self) -> usize {
+        self.data.len()
+    }
+    fn bump(&mut self) {
+        self.slot += 10;
+    }
+    fn into_slot(self) -> u64 {
+        self.slot
+    }
+}
+
+impl Weigh for PacketHuge {
+    fn weight(&self) -> usize {
+        self.extra.len()
+    }
+    fn bump(&mut self) {
+        self.slot += 100;
+    }
+    fn into_slot(self) -> u64 {
+        self.slot
+    }
+}
+
+#[delegate(Packet)]
+impl Weigh for Packet {
+    fn weight(&self) -> usize;
+    fn bump(&mut self);
+    fn into_slot(self) -> u64;
+}
+
+struct LargeSlots(u64);
+
+impl PacketVisitor for LargeSlots {
+    type Output = ();
+
+    fn visit_any<V: PacketVariant>(&mut self, _: &V) {}
+
+    fn visit_large(&mut self, variant: &PacketLarge) {
+        self.0 += variant.slot;
+    }
+}
+
+#[test]
+fn auto_boxed_variant() {
+    // Only `Large` is boxed automatically, and `Huge` is boxed explicitly despite being small.
+    assert!(size_of::<Packet>() < 64);
+    let small = Packet::Small(PacketSmall { slot: 1 });
+    let mut large = Packet::from(PacketLarge {
+        slot: 2,
+        data: [7; 128],
+    });
+    let huge = Packet::Huge(Box::new(PacketHuge {
+        slot: 3,
+        extra: [0; 32],
+    }));
+    assert!(matches!(small, Packet::Small(PacketSmall { slot: 1 })));
+    assert!(matches!(large, Packet::Large(ref inner) if inner.data[0] == 7));
+
+    assert_eq!(large.slot(), 2);
+    assert_eq!(large.data().map(|data| data[1]), Ok(7));
+    *large.slot_mut() = 4;
+    assert_eq!(large.as_large().map(|inner| inner.slot), Ok(4));
+    large.as_large_mut().unwrap().data[0] = 8;
+    assert_eq!(large.to_ref().data().map(|data| data[0]), Ok(8));
+    *large.to_mut().slot_mut() = 5;
+    assert_eq!(large.to_ref().slot(), 5);
+
+    assert_eq!(large.to_ref().cloned(), large);
+    assert_eq!(PacketCow::from(&large).into_owned(), large);
+    assert_eq!(Packet::from(PacketCow::from(large.clone())), large);
+    assert!(large.to_ref() == large);
+    let large_inner = PacketLarge::from_enum(large.clone()).unwrap();
+    assert_eq!(large_inner.data[..2], [8, 7]);
+    assert_eq!(large_inner.clone().into_enum(), large);
+    assert_eq!(large.try_get::<PacketLarge>(), Some(&large_inner));
+
+    let mut large_slots = LargeSlots(0);
+    for packet in [&small, &large, &huge] {
+        packet.accept(&mut large_slots);
+    }
+    assert_eq!(large_slots.0, 5);
+
+    assert_eq!(large.weight(), 128);
+    assert_eq!(huge.weight(), 32);
+    large.bump();
+    assert_eq!(large.clone().into_slot(), 15);
+
+    for packet in [small, large, huge] {
+        let slot = packet.slot();
+        let summary =
+            map_packet_into_summary!(packet.clone(), |inner, cons| cons(inner.into_summary()));
+        assert_eq!(*summary.slot(), slot);
+        let doubled = map_packet!(packet, |mut inner, cons| {
+            inner.slot *= 2;
+            cons(inner)
+        });
+        assert_eq!(doubled.slot(), slot * 2);
+    }
+}
+
+#[test]
+fn auto_boxed_meta() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(Base, Altair),
+        variant_attributes(derive(Debug, Clone, PartialEq)),
+        box_variants_larger_than = 16,
+        variant_trait,
+        no_map_macros
+    )]
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Payload {
+        slot: u64,
+        #[superstruct(meta_only(Full))]
+        transactions: Vec<u8>,
+    }
+
+    // Only the `Full` variant structs, which hold a `Vec`, are boxed.
+    assert_eq!(size_of::<PayloadFull>(), size_of::<Box<()>>() * 2);
+    assert_eq!(size_of::<PayloadBlinded>(), size_of::<u64>() * 2);
+
+    let mut payload = Payload::Full(PayloadFull::from(PayloadFullAltair {
+        slot: 1,
+        transactions: vec![2],
+    }));
+    assert!(matches!(payload, Payload::Full(PayloadFull::Altair(_))));
+    assert_eq!(*payload.slot(), 1);
+    assert_eq!(payload.transactions(), Ok(&vec![2]));
+    *payload.slot_mut() = 5;
+    assert_eq!(*payload.to_ref().slot(), 5);
+
+    let full = PayloadFull::from(PayloadFullBase {
+        slot: 6,
+        transactions: vec![],
+    });
+    assert_eq!(
+        PayloadFullBase::from_enum(full.clone()).map(|inner| inner.slot),
+        Ok(6)
+    );
+    assert_eq!(PayloadFullAltair::from_enum(full.clone()), Err(full));
+}
@@ -0,0 +1,99 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use std::borrow::Cow;
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Base, Altair),
+    variant_attributes(derive(Debug, Clone, PartialEq)),
+    cow,
+    variant_kind
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct State<T> {
+    slot: u64,
+    balances: Vec<T>,
+    #[superstruct(only(Altair), partial_getter(copy))]
+    participation: u8,
+}
+
+#[test]
+fn cow_borrowed_and_owned() {
+    let state = State::Altair(StateAltair {
+        slot: 1,
+        balances: vec![10u64],
+        participation: 3,
+    });
+
+    let mut cow = StateCow::from(&state);
+    assert!(matches!(cow, StateCow::Altair(Cow::Borrowed(_))));
+    assert_eq!(*cow.slot(), 1);
+    assert_eq!(cow.balances(), &[10]);
+    assert_eq!(cow.participation(), Ok(3));
+    assert_eq!(cow.variant_kind(), StateKind::Altair);
+
+    // Promote to owned on the first mutation, leaving the original untouched.
+    *cow.to_mut().slot_mut() = 2;
+    assert!(matches!(cow, StateCow::Altair(Cow::Owned(_))));
+    assert_eq!(*cow.slot(), 2);
+    assert_eq!(*state.slot(), 1);
+
+    let owned = cow.clone().into_owned();
+    assert_eq!(owned.slot(), &2);
+    assert_eq!(State::from(cow), owned);
+
+    let base = StateCow::from(State::<u64>::Base(StateBase {
+        slot: 4,
+        balances: vec![],
+    }));
+    assert!(matches!(base, StateCow::Base(Cow::Owned(_))));
+    assert!(base.participation().is_err());
+    assert_eq!(base.variant_kind(), StateKind::Base);
+
+    let base_inner = StateBase {
+        slot: 5,
+        balances: vec![1u64],
+    };
+    let from_ref = StateCow::from(StateRef::from(&base_inner));
+    assert_eq!(from_ref.into_owned().balances(), &[1]);
+}
+
+#[test]
+fn cow_meta() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(Base, Altair),
+        variant_attributes(derive(Debug, Clone, PartialEq)),
+        cow,
+        variant_kind,
+        no_map_macros
+    )]
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Block {
+        slot: u64,
+        #[superstruct(meta_only(Full))]
+        transactions: Vec<u8>,
+    }
+
+    let block = Block::Full(BlockFull::Altair(BlockFullAltair {
+        slot: 1,
+        transactions: vec![2],
+    }));
+    let mut cow = BlockCow::from(&block);
+    assert_eq!(*cow.slot(), 1);
+    assert_eq!(cow.transactions(), Ok(&vec![2]));
+    assert_eq!(cow.variant_kind(), BlockKind::Full);
+
+    *cow.to_mut().slot_mut() = 3;
+    assert!(matches!(cow, BlockCow::Full(Cow::Owned(_))));
+    assert_eq!(*block.slot(), 1);
+    assert_eq!(*cow.into_owned().slot(), 3);
+
+    let blinded = BlockBlinded::Base(BlockBlindedBase { slot: 4 });
+    let inner_cow = BlockBlindedCow::from(blinded.to_ref());
+    assert_eq!(*inner_cow.slot(), 4);
+    assert_eq!(inner_cow.variant_kind(), BlockBlindedKind::Base);
+    let outer_cow = BlockCow::from(Block::Blinded(inner_cow.into_owned()));
+    assert!(outer_cow.transactions().is_err());
+    assert_eq!(outer_cow.variant_kind(), BlockKind::Blinded);
+}
@@ -0,0 +1,326 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use std::fmt;
+use superstruct::{delegatable, delegate, superstruct};
+
+#[superstruct(
+    variants(Circle, Square),
+    variant_attributes(derive(Debug, Clone, PartialEq))
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    size: u64,
+    #[superstruct(only(Circle))]
+    name: String,
+}
+
+pub trait Measure {
+    type Unit;
+
+    fn measure(&self) -> Self::Unit;
+    fn scale<T: Into<u64>>(&mut self, factor: T);
+    fn doubled(self) -> Self;
+}
+
+impl Measure for ShapeCircle {
+    type Unit = u64;
+
+    fn measure(&self) -> u64 {
+        self.size * 3
+    }
+
+    fn scale<T: Into<u64>>(&mut self, factor: T) {
+        self.size *= factor.into();
+    }
+
+    fn doubled(self) -> Self {
+        Self {
+            size: self.size * 2,
+            name: self.name.repeat(2),
+        }
+    }
+}
+
+impl Measure for ShapeSquare {
+    type Unit = u64;
+
+    fn measure(&self) -> u64 {
+        self.size * 4
+    }
+
+    fn scale<T: Into<u64>>(&mut self, factor: T) {
+        self.size *= factor.into() * 2;
+    }
+
+    fn doubled(self) -> Self {
+        Self {
+            size: self.size * 2,
+        }
+    }
+}
+
+#[delegate(Shape)]
+impl Measure for Shape {
+    type Unit = u64;
+
+    fn measure(&self) -> Self::Unit;
+    fn scale<T: Into<u64>>(&mut self, factor: T);
+    fn doubled(self) -> Self;
+}
+
+impl fmt::Display for ShapeCircle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circle {} of size {}", self.name, self.size)
+    }
+}
+
+impl fmt::Display for ShapeSquare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "square of size {}", self.size)
+    }
+}
+
+#[delegate(Shape)]
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+#[delegate(Shape)]
+impl fmt::Display for ShapeRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+#[delegate(Shape)]
+impl Measure for ShapeRefMut<'_> {
+    type Unit = u64;
+
+    fn measure(&self) -> u64;
+    fn scale<T: Into<u64>>(&mut self, factor: T);
+
+    fn doubled(self) -> Self {
+        self
+    }
+}
+
+#[delegatable]
+pub trait Area {
+    type Output;
+    const SIDES: u32;
+
+    fn area(&self) -> Self::Output;
+
+    fn describe(&self) -> String {
+        "shape".into()
+    }
+
+    fn unit() -> &'static str {
+        "m2"
+    }
+}
+
+impl Area for ShapeCircle {
+    type Output = u64;
+    const SIDES: u32 = 0;
+
+    fn area(&self) -> u64 {
+        self.size * self.size * 3
+    }
+
+    fn describe(&self) -> String {
+        format!("circle {}", self.name)
+    }
+}
+
+impl Area for ShapeSquare {
+    type Output = u64;
+    const SIDES: u32 = 0;
+
+    fn area(&self) -> u64 {
+        self.size * self.size
+    }
+}
+
+// The items of a `delegatable` trait don't need to be declared.
+#[delegate(Shape)]
+impl Area for Shape {}
+
+#[test]
+fn delegate_enum() {
+    let mut circle = Shape::Circle(ShapeCircle {
+        size: 1,
+        name: "c".into(),
+    });
+    let mut square = Shape::Square(ShapeSquare { size: 1 });
+
+    assert_eq!(circle.measure(), 3);
+    assert_eq!(square.measure(), 4);
+
+    circle.scale(2u8);
+    square.scale(2u32);
+    assert_eq!(*circle.size(), 2);
+    assert_eq!(*square.size(), 4);
+
+    assert_eq!(
+        circle.doubled(),
+        Shape::Circle(ShapeCircle {
+            size: 4,
+            name: "cc".into(),
+        })
+    );
+    assert_eq!(square.to_string(), "square of size 4");
+}
+
+#[test]
+fn delegate_ref() {
+    let mut circle = Shape::Circle(ShapeCircle {
+        size: 1,
+        name: "c".into(),
+    });
+    assert_eq!(circle.to_ref().to_string(), "circle c of size 1");
+
+    let mut circle_mut = circle.to_mut();
+    circle_mut.scale(5u8);
+    assert_eq!(circle_mut.measure(), 15);
+    assert_eq!(*circle_mut.doubled().size_mut(), 5);
+}
+
+#[test]
+fn delegate_trait_items() {
+    let circle = Shape::Circle(ShapeCircle {
+        size: 2,
+        name: "c".into(),
+    });
+    let square = Shape::Square(ShapeSquare { size: 3 });
+
+    assert_eq!(circle.area(), 12);
+    assert_eq!(square.area(), 9);
+    assert_eq!(circle.describe(), "circle c");
+    assert_eq!(square.describe(), "shape");
+    assert_eq!(Shape::SIDES, 0);
+    assert_eq!(Shape::unit(), "m2");
+}
+
+#[test]
+fn delegate_boxed_without_map_macros() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, Clone, PartialEq)),
+        boxed(B),
+        no_map_macros
+    )]
+    #[derive(Debug, PartialEq)]
+    pub struct Counter {
+        count: u64,
+    }
+
+    pub trait Bump {
+        fn bump(&mut self);
+        fn into_count(self) -> u64;
+    }
+
+    impl Bump for CounterA {
+        fn bump(&mut self) {
+            self.count += 1;
+        }
+
+        fn into_count(self) -> u64 {
+            self.count
+        }
+    }
+
+    impl Bump for CounterB {
+        fn bump(&mut self) {
+            self.count += 2;
+        }
+
+        fn into_count(self) -> u64 {
+            self.count
+        }
+    }
+
+    #[delegate(Counter)]
+    impl Clone for Counter {
+        fn clone(&self) -> Self;
+    }
+
+    #[delegate(Counter)]
+    impl Bump for Counter {
+        fn bump(&mut self);
+        fn into_count(self) -> u64;
+    }
+
+    let mut counter = Counter::B(Box::new(CounterB { count: 1 }));
+    assert_eq!(counter.clone(), counter);
+    counter.bump();
+    assert_eq!(counter.into_count(), 3);
+}
+
+// The superstruct, the trait and the impls in different modules, using imports.
+mod polygons {
+    use superstruct::superstruct;
+
+    #[superstruct(
+        variants(Triangle, Square),
+        variant_attributes(derive(Debug)),
+        no_map_macros
+    )]
+    pub struct Polygon {
+        pub side: u64,
+    }
+
+    impl super::perimeter::Perimeter for PolygonTriangle {
+        fn perimeter(&self) -> u64 {
+            self.side * 3
+        }
+    }
+
+    impl super::perimeter::Perimeter for PolygonSquare {
+        fn perimeter(&self) -> u64 {
+            self.side * 4
+        }
+    }
+}
+
+mod perimeter {
+    use superstruct::delegatable;
+
+    #[delegatable]
+    pub trait Perimeter {
+        fn perimeter(&self) -> u64;
+    }
+}
+
+mod polygon_impls {
+    use super::perimeter::Perimeter;
+    use super::polygons::{Polygon, PolygonRef};
+    use std::fmt;
+    use superstruct::delegate;
+
+    #[delegate(Polygon)]
+    impl Perimeter for Polygon {}
+
+    #[delegate(Polygon)]
+    impl Perimeter for PolygonRef<'_> {}
+
+    #[delegate(Polygon)]
+    impl fmt::Debug for PolygonRef<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+    }
+}
+
+#[test]
+fn delegate_imported_types() {
+    use perimeter::Perimeter;
+    use polygons::*;
+
+    let square = Polygon::Square(PolygonSquare { side: 2 });
+    assert_eq!(square.perimeter(), 8);
+    assert_eq!(square.to_ref().perimeter(), 8);
+    assert_eq!(
+        format!(
+            "{:?}",
+            Polygon::Triangle(PolygonTriangle { side: 1 }).to_ref()
+        ),
+        "PolygonTriangle { side: 1 }"
+    );
+}
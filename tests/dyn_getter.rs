@@ -0,0 +1,112 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use std::fmt::{Debug, Display};
+use superstruct::superstruct;
+
+#[test]
+fn dyn_getter() {
+    #[superstruct(variants(A, B, C))]
+    struct Message {
+        #[superstruct(only(A), getter(dyn = "Display"))]
+        pub x: u64,
+        #[superstruct(only(B, C), getter(dyn = "Display"))]
+        pub x: String,
+        #[superstruct(only(A), getter(dyn = "Debug + Send", rename = "payload", no_mut))]
+        pub y: u8,
+        #[superstruct(only(B), getter(dyn = "Debug + Send", rename = "payload", no_mut))]
+        pub z: Vec<u8>,
+    }
+
+    let mut a = Message::A(MessageA { x: 1, y: 2 });
+    let mut b = Message::B(MessageB {
+        x: "hello".into(),
+        z: vec![3],
+    });
+    let c = Message::C(MessageC { x: "world".into() });
+
+    // Total getters.
+    assert_eq!(a.x().to_string(), "1");
+    assert_eq!(b.x().to_string(), "hello");
+    assert_eq!(c.x().to_string(), "world");
+    assert_eq!(a.to_ref().x().to_string(), "1");
+    assert_eq!(b.to_mut().x_mut().to_string(), "hello");
+
+    // Partial getters.
+    assert_eq!(format!("{:?}", a.payload().unwrap()), "2");
+    assert_eq!(format!("{:?}", b.to_ref().payload().unwrap()), "[3]");
+    assert!(c.payload().is_err());
+
+    let _: &mut dyn Display = a.x_mut();
+}
+
+#[test]
+fn dyn_getter_common_field() {
+    trait Describe {
+        fn describe(&self) -> String;
+    }
+
+    impl Describe for u64 {
+        fn describe(&self) -> String {
+            format!("u64 {self}")
+        }
+    }
+
+    #[superstruct(variants(A, B))]
+    struct Payload {
+        #[superstruct(getter(dyn = "Describe"))]
+        pub x: u64,
+        #[superstruct(only(B))]
+        pub y: u64,
+    }
+
+    fn describe_x(payload: &Payload) -> &dyn Describe {
+        payload.to_ref().x()
+    }
+
+    let b = Payload::B(PayloadB { x: 5, y: 6 });
+    assert_eq!(describe_x(&b).describe(), "u64 5");
+    assert_eq!(*b.y().unwrap(), 6);
+}
+
+#[test]
+fn dyn_getter_meta_variants() {
+    #[superstruct(meta_variants(Full, Blinded), variants(A, B))]
+    struct Block {
+        #[superstruct(meta_only(Full), getter(dyn = "Debug"))]
+        pub payload: Vec<u8>,
+        #[superstruct(meta_only(Blinded), only(A), getter(dyn = "Debug"))]
+        pub payload: u64,
+    }
+
+    let full = Block::Full(BlockFull::B(BlockFullB { payload: vec![1] }));
+    let blinded_a = Block::Blinded(BlockBlinded::A(BlockBlindedA { payload: 2 }));
+    let blinded_b = Block::Blinded(BlockBlinded::B(BlockBlindedB {}));
+
+    assert_eq!(format!("{:?}", full.payload().unwrap()), "[1]");
+    assert_eq!(format!("{:?}", blinded_a.payload().unwrap()), "2");
+    assert!(blinded_b.payload().is_err());
+
+    // The inner enum for `Full` has the field in all variants, so its getter is total.
+    let full_inner = BlockFull::A(BlockFullA { payload: vec![] });
+    assert_eq!(format!("{:?}", full_inner.payload()), "[]");
+}
+
+#[test]
+#[allow(clippy::non_minimal_cfg)]
+fn dyn_getter_cfg() {
+    #[superstruct(variants(A, B), no_map_macros)]
+    struct Message {
+        #[cfg(not(all()))]
+        #[superstruct(only(A), getter(dyn = "Display"))]
+        pub x: u64,
+        #[cfg(not(all()))]
+        #[superstruct(only(B), getter(dyn = "Display"))]
+        pub x: String,
+        #[cfg(all())]
+        #[superstruct(getter(dyn = "Display"))]
+        pub y: u8,
+    }
+
+    let message = Message::A(MessageA { y: 1 });
+    assert_eq!(message.y().to_string(), "1");
+}
@@ -0,0 +1,49 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde::Serialize;
+use superstruct::superstruct;
+
+#[test]
+#[allow(dead_code)]
+fn field_attributes() {
+    #[superstruct(variants(A, B, C), variant_attributes(derive(Serialize)))]
+    struct Message {
+        #[superstruct(field_attributes(B(serde(rename = "blob_gas")), C(serde(skip))))]
+        pub gas: u64,
+        #[superstruct(only(B, C), field_attributes(C(serde(rename = "extra"))))]
+        pub data: u8,
+    }
+
+    let a = MessageA { gas: 1 };
+    let b = MessageB { gas: 2, data: 3 };
+    let c = MessageC { gas: 4, data: 5 };
+
+    assert_eq!(serde_json::to_string(&a).unwrap(), r#"{"gas":1}"#);
+    assert_eq!(
+        serde_json::to_string(&b).unwrap(),
+        r#"{"blob_gas":2,"data":3}"#
+    );
+    assert_eq!(serde_json::to_string(&c).unwrap(), r#"{"extra":5}"#);
+}
+
+#[test]
+#[allow(dead_code)]
+fn field_attributes_meta_variants() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(A, B),
+        variant_attributes(derive(Serialize))
+    )]
+    struct Block {
+        #[superstruct(field_attributes(Blinded(serde(rename = "root")), B(serde(skip))))]
+        pub payload: u64,
+    }
+
+    let full_a = BlockFullA { payload: 1 };
+    let blinded_a = BlockBlindedA { payload: 2 };
+    let blinded_b = BlockBlindedB { payload: 3 };
+
+    assert_eq!(serde_json::to_string(&full_a).unwrap(), r#"{"payload":1}"#);
+    assert_eq!(serde_json::to_string(&blinded_a).unwrap(), r#"{"root":2}"#);
+    assert_eq!(serde_json::to_string(&blinded_b).unwrap(), r#"{}"#);
+}
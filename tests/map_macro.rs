@@ -133,3 +133,104 @@ fn map_into() {
     *thing1_ref_mut_to_thing2_ref_mut(thing1.to_mut()).x_mut() = 11;
     assert_eq!(*thing1_ref_to_thing2_ref(thing1.to_ref()).x(), 11);
 }
+
+#[test]
+fn map_macro_lifetime_and_const_generics() {
+    #[superstruct(variants(Base, Ext), variant_attributes(derive(Debug, PartialEq)))]
+    #[derive(Debug, PartialEq)]
+    pub struct Packet<'a, T, const N: usize> {
+        bytes: [u8; N],
+        name: &'a str,
+        #[superstruct(only(Ext))]
+        extra: T,
+    }
+
+    fn increment<'a, T, const N: usize>(packet: Packet<'a, T, N>) -> Packet<'a, T, N> {
+        map_packet!(packet, |mut inner, cons| {
+            inner.bytes[0] += 1;
+            cons(inner)
+        })
+    }
+
+    fn get_name<'a, 'b, T, const N: usize>(packet: PacketRef<'a, 'b, T, N>) -> &'a str {
+        map_packet_ref!(&'a _, packet, |inner, cons| {
+            let _ = cons(inner);
+            inner.name
+        })
+    }
+
+    fn clear_name<'a, 'b, T, const N: usize>(packet: PacketRefMut<'a, 'b, T, N>) {
+        map_packet_ref_mut!(&'a _, packet, |inner, _| inner.name = "")
+    }
+
+    let name = String::from("hello");
+    let mut packet = increment(Packet::Ext(PacketExt {
+        bytes: [0; 2],
+        name: &name,
+        extra: 5u64,
+    }));
+    assert_eq!(packet.bytes(), &[1, 0]);
+    assert_eq!(get_name(packet.to_ref()), "hello");
+
+    clear_name(packet.to_mut());
+    assert_eq!(*packet.name(), "");
+
+    // The constructor can be discarded, even though its generic arguments can't be inferred.
+    let len = map_packet!(packet, move |inner, _| -> usize { inner.bytes.len() });
+    assert_eq!(len, 2);
+}
+
+#[test]
+fn map_macro_closure() {
+    #[superstruct(variants(Base, Ext), variant_attributes(derive(Debug, PartialEq)))]
+    #[derive(Debug, PartialEq)]
+    pub struct Counter {
+        count: u64,
+        #[superstruct(only(Ext))]
+        name: String,
+    }
+
+    fn add(counter: Counter, amount: u64) -> Counter {
+        map_counter!(
+            counter,
+            closure(|mut inner, cons| {
+                inner.count += amount;
+                cons(inner)
+            })
+        )
+    }
+
+    fn add_all(counter: &mut Counter, amounts: &[u64]) {
+        let mut total = 0;
+        map_counter_ref_mut!(
+            &'_ _,
+            counter.to_mut(),
+            closure(move |inner, _| {
+                for amount in amounts {
+                    inner.count += amount;
+                    total += amount;
+                }
+            })
+        );
+        assert_eq!(total, amounts.iter().sum::<u64>());
+    }
+
+    let mut counter = add(Counter::Base(CounterBase { count: 1 }), 2);
+    assert_eq!(counter, Counter::Base(CounterBase { count: 3 }));
+
+    add_all(&mut counter, &[1, 2, 3]);
+    assert_eq!(*counter.count(), 9);
+
+    let names = ["hello".to_string()];
+    let counter = Counter::Ext(CounterExt {
+        count: 0,
+        name: "world".into(),
+    });
+    let known = map_counter_ref!(
+        &'_ _,
+        counter.to_ref(),
+        closure(|inner, _| names.contains(&inner.count.to_string()))
+    );
+    assert!(!known);
+    assert_eq!(counter.name().unwrap(), "world");
+}
@@ -0,0 +1,80 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[superstruct(
+    meta_variants(Read, Write),
+    variants(Lower, Upper),
+    variant_attributes(derive(Debug, PartialEq))
+)]
+#[derive(Debug, PartialEq)]
+pub struct Note<T> {
+    pub id: T,
+    #[superstruct(meta_only(Write))]
+    pub text: String,
+}
+
+#[test]
+fn map_leaf_owned() {
+    fn increment<T: std::ops::AddAssign + From<u8>>(note: Note<T>) -> Note<T> {
+        map_note_leaf!(note, |mut inner, cons| {
+            inner.id += T::from(1);
+            cons(inner)
+        })
+    }
+
+    fn append(note: Note<u64>, suffix: &str) -> Note<u64> {
+        map_note_leaf!(
+            note,
+            closure(|inner, cons| {
+                let mut note = cons(inner);
+                if let Ok(text) = note.text_mut() {
+                    text.push_str(suffix);
+                }
+                note
+            })
+        )
+    }
+
+    let note = Note::Write(NoteWrite::Upper(NoteWriteUpper {
+        id: 1u64,
+        text: "a".into(),
+    }));
+    assert_eq!(
+        append(increment(note), "b"),
+        Note::Write(NoteWrite::Upper(NoteWriteUpper {
+            id: 2,
+            text: "ab".into(),
+        }))
+    );
+    assert_eq!(
+        increment(Note::Read(NoteRead::Lower(NoteReadLower { id: 1u8 }))),
+        Note::Read(NoteRead::Lower(NoteReadLower { id: 2 }))
+    );
+
+    // The constructor can be discarded, even though its generic arguments can't be inferred.
+    let note = Note::Read(NoteRead::Upper(NoteReadUpper { id: 3u32 }));
+    assert_eq!(map_note_leaf!(note, |inner, _| inner.id), 3);
+}
+
+#[test]
+fn map_leaf_ref() {
+    fn id<'a, T>(note: NoteRef<'a, T>) -> &'a T {
+        map_note_ref_leaf!(&'a _, note, |inner, cons| {
+            let _ = cons(inner);
+            &inner.id
+        })
+    }
+
+    fn set_id<T: Copy>(note: &mut Note<T>, id: T) {
+        map_note_ref_mut_leaf!(&'_ _, note.to_mut(), closure(|inner, _| inner.id = id))
+    }
+
+    let mut note = Note::Write(NoteWrite::Lower(NoteWriteLower {
+        id: 1,
+        text: "a".into(),
+    }));
+    assert_eq!(*id(note.to_ref()), 1);
+    set_id(&mut note, 5);
+    assert_eq!(*id(note.to_ref()), 5);
+}
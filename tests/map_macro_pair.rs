@@ -0,0 +1,92 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[superstruct(variants(Base, Ext), variant_attributes(derive(Debug, PartialEq)))]
+#[derive(Debug, PartialEq)]
+pub struct Tally {
+    count: u64,
+    #[superstruct(only(Ext))]
+    name: String,
+}
+
+#[test]
+fn map_pair_owned() {
+    fn merge(a: Tally, b: Tally) -> Result<Tally, (Tally, Tally)> {
+        map_tally_pair!(
+            a,
+            b,
+            |mut inner_a, inner_b, cons| {
+                inner_a.count += inner_b.count;
+                Ok(cons(inner_a))
+            },
+            |a, b| Err((a, b))
+        )
+    }
+
+    let merged = merge(
+        Tally::Base(TallyBase { count: 1 }),
+        Tally::Base(TallyBase { count: 2 }),
+    );
+    assert_eq!(merged, Ok(Tally::Base(TallyBase { count: 3 })));
+
+    let base = Tally::Base(TallyBase { count: 1 });
+    let ext = Tally::Ext(TallyExt {
+        count: 2,
+        name: "ext".into(),
+    });
+    assert_eq!(merge(base, ext).unwrap_err().1.name().unwrap(), "ext");
+}
+
+#[test]
+fn map_pair_ref() {
+    fn count_diff<'a>(a: TallyRef<'a>, b: TallyRef<'a>) -> Option<i64> {
+        map_tally_ref_pair!(
+            &'a _,
+            a,
+            b,
+            |inner_a, inner_b, _| Some(inner_b.count as i64 - inner_a.count as i64),
+            |_, _| None
+        )
+    }
+
+    let a = Tally::Ext(TallyExt {
+        count: 5,
+        name: "a".into(),
+    });
+    let b = Tally::Ext(TallyExt {
+        count: 3,
+        name: "b".into(),
+    });
+    let c = Tally::Base(TallyBase { count: 3 });
+    assert_eq!(count_diff(a.to_ref(), b.to_ref()), Some(-2));
+    assert_eq!(count_diff(a.to_ref(), c.to_ref()), None);
+}
+
+#[test]
+fn map_pair_ref_mut_closure() {
+    fn transfer(a: &mut Tally, b: &mut Tally, amount: u64) -> bool {
+        map_tally_ref_mut_pair!(
+            &'_ _,
+            a.to_mut(),
+            b.to_mut(),
+            closure(|inner_a, inner_b, _| {
+                inner_a.count -= amount;
+                inner_b.count += amount;
+                true
+            }),
+            |_, _| false
+        )
+    }
+
+    let mut a = Tally::Base(TallyBase { count: 5 });
+    let mut b = Tally::Base(TallyBase { count: 0 });
+    let mut c = Tally::Ext(TallyExt {
+        count: 0,
+        name: "c".into(),
+    });
+    assert!(transfer(&mut a, &mut b, 2));
+    assert_eq!((*a.count(), *b.count()), (3, 2));
+    assert!(!transfer(&mut a, &mut c, 2));
+    assert_eq!((*a.count(), *c.count()), (3, 0));
+}
@@ -0,0 +1,66 @@
+mod types {
+    use superstruct::superstruct;
+
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq)),
+        map_into(Other),
+        map_macro_vis = "crate",
+        map_macro_path = "crate::types"
+    )]
+    #[derive(Debug, PartialEq)]
+    pub struct Message {
+        pub x: u64,
+    }
+
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq)),
+        map_macro_vis = "pub",
+        map_macro_path = "$crate::types"
+    )]
+    #[derive(Debug, PartialEq)]
+    pub struct Other {
+        pub y: u64,
+    }
+}
+
+// Neither `Message` nor `Other` (nor their variants) are in scope here.
+mod users {
+    pub fn increment(message: crate::types::Message) -> crate::types::Message {
+        crate::types::map_message!(message, |mut inner, cons| {
+            inner.x += 1;
+            cons(inner)
+        })
+    }
+
+    pub fn convert(message: crate::types::Message) -> crate::types::Other {
+        crate::types::map_message_into_other!(message, |inner, cons| { cons(inner.into()) })
+    }
+
+    pub fn get_y(other: crate::types::OtherRef<'_>) -> u64 {
+        crate::types::map_other_ref!(&'_ _, other, |inner, _| inner.y)
+    }
+}
+
+impl From<types::MessageA> for types::OtherA {
+    fn from(message: types::MessageA) -> Self {
+        Self { y: message.x }
+    }
+}
+
+impl From<types::MessageB> for types::OtherB {
+    fn from(message: types::MessageB) -> Self {
+        Self { y: message.x }
+    }
+}
+
+#[test]
+fn map_macro_path() {
+    let message = types::Message::B(types::MessageB { x: 1 });
+    let message = users::increment(message);
+    assert_eq!(message, types::Message::B(types::MessageB { x: 2 }));
+
+    let other = users::convert(message);
+    assert_eq!(users::get_y(other.to_ref()), 2);
+}
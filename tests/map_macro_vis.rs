@@ -0,0 +1,122 @@
+// Two superstructs with the same name in different modules, whose map macros don't collide.
+mod first {
+    use superstruct::superstruct;
+
+    #[superstruct(variants(A, B), map_macro_vis = "crate")]
+    pub struct Message {
+        pub x: u64,
+    }
+}
+
+mod second {
+    use superstruct::superstruct;
+
+    #[superstruct(variants(A, B), map_macro_vis = "crate")]
+    pub struct Message {
+        pub y: u64,
+    }
+}
+
+pub mod public {
+    use superstruct::superstruct;
+
+    #[superstruct(variants(A, B), map_macro_vis = "pub")]
+    pub struct Message {
+        pub z: u64,
+    }
+}
+
+// Another public type with the same name, which needs a different hidden name for its macros.
+pub mod other_public {
+    use superstruct::superstruct;
+
+    #[superstruct(
+        variants(A, B),
+        map_macro_vis = "pub",
+        map_macro_disambiguator = "other_public"
+    )]
+    pub struct Message {
+        pub v: u64,
+    }
+}
+
+// A third public type with the same name, disambiguated by the modules of its `map_macro_path`.
+pub mod pathed {
+    use superstruct::superstruct;
+
+    #[superstruct(
+        variants(A, B),
+        map_macro_vis = "pub",
+        map_macro_path = "$crate::pathed"
+    )]
+    pub struct Message {
+        pub u: u64,
+    }
+}
+
+mod private {
+    use superstruct::superstruct;
+
+    #[superstruct(variants(A, B), map_macro_vis = "module")]
+    pub struct Message {
+        pub w: u64,
+    }
+
+    pub fn get_w(message: Message) -> u64 {
+        self::map_message!(message, |inner, _| inner.w)
+    }
+
+    pub mod child {
+        use super::*;
+
+        pub fn get_w_ref(message: MessageRef<'_>) -> u64 {
+            super::map_message_ref!(&'_ _, message, |inner, _| inner.w)
+        }
+    }
+}
+
+// The macros refer to types by name, so these must be in scope where the macros are used.
+#[test]
+fn map_macro_vis_crate() {
+    {
+        use first::*;
+        let first = Message::A(MessageA { x: 1 });
+        assert_eq!(crate::first::map_message!(first, |inner, _| inner.x), 1);
+    }
+    {
+        use second::*;
+        let second = Message::B(MessageB { y: 2 });
+        assert_eq!(second::map_message!(second, |inner, _| inner.y), 2);
+    }
+}
+
+#[test]
+fn map_macro_vis_pub() {
+    {
+        use public::*;
+        let message = Message::A(MessageA { z: 3 });
+
+        assert_eq!(
+            public::map_message_ref!(&'_ _, message.to_ref(), |inner, _| inner.z),
+            3
+        );
+        assert_eq!(public::map_message!(message, |inner, _| inner.z), 3);
+    }
+    {
+        use other_public::*;
+        let message = Message::B(MessageB { v: 5 });
+        assert_eq!(other_public::map_message!(message, |inner, _| inner.v), 5);
+    }
+    {
+        let message = pathed::Message::A(pathed::MessageA { u: 6 });
+        assert_eq!(pathed::map_message!(message, |inner, _| inner.u), 6);
+    }
+}
+
+#[test]
+fn map_macro_vis_module() {
+    let message = private::Message::B(private::MessageB { w: 4 });
+
+    assert_eq!(private::child::get_w_ref(message.to_ref()), 4);
+    assert_eq!(private::get_w(message), 4);
+}
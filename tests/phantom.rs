@@ -0,0 +1,107 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use superstruct::superstruct;
+
+#[test]
+fn phantom_type_param() {
+    #[superstruct(variants(A, B), variant_attributes(derive(Debug, PartialEq)))]
+    #[derive(Debug, PartialEq)]
+    struct Message<T> {
+        id: u64,
+        #[superstruct(only(B))]
+        payload: T,
+    }
+
+    let a: Message<String> = Message::A(MessageA {
+        id: 1,
+        _phantom: PhantomData,
+    });
+    let b = Message::B(MessageB {
+        id: 2,
+        payload: "hello".to_string(),
+    });
+
+    assert_eq!(*a.id(), 1);
+    assert!(a.payload().is_err());
+    assert_eq!(b.payload().unwrap(), "hello");
+}
+
+#[test]
+fn phantom_lifetime_and_type_params() {
+    #[superstruct(variants(A, B, C), variant_attributes(derive(Debug, PartialEq)))]
+    #[derive(Debug, PartialEq)]
+    struct Packet<'a, T: 'a, const N: usize> {
+        bytes: [u8; N],
+        #[superstruct(only(B))]
+        payload: &'a T,
+        #[superstruct(only(C))]
+        name: &'a str,
+    }
+
+    let a: PacketA<'_, u64, 2> = PacketA {
+        bytes: [0; 2],
+        _phantom: PhantomData,
+    };
+    let b = PacketB {
+        bytes: [1; 2],
+        payload: &5u64,
+    };
+    let c: PacketC<'_, u64, 2> = PacketC {
+        bytes: [2; 2],
+        name: "hello",
+        _phantom: PhantomData,
+    };
+
+    assert_eq!(Packet::A(a).bytes(), &[0; 2]);
+    assert_eq!(**Packet::B(b).payload().unwrap(), 5);
+    assert_eq!(*Packet::C(c).name().unwrap(), "hello");
+}
+
+#[test]
+fn phantom_attributes() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq, Serialize, Deserialize)),
+        phantom_attributes(serde(skip))
+    )]
+    struct Envelope<T> {
+        id: u64,
+        #[superstruct(only(B))]
+        payload: T,
+    }
+
+    let a: EnvelopeA<String> = EnvelopeA {
+        id: 1,
+        _phantom: PhantomData,
+    };
+    let json = serde_json::to_string(&a).unwrap();
+    assert_eq!(json, r#"{"id":1}"#);
+    assert_eq!(serde_json::from_str::<EnvelopeA<String>>(&json).unwrap(), a);
+}
+
+#[test]
+fn phantom_serde_skipped_by_default() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(
+            derive(Debug, PartialEq),
+            cfg_attr(all(), derive(Serialize, Deserialize))
+        ),
+        no_map_macros
+    )]
+    struct Envelope<T> {
+        id: u64,
+        #[superstruct(only(B))]
+        payload: T,
+    }
+
+    let a: EnvelopeA<String> = EnvelopeA {
+        id: 1,
+        _phantom: PhantomData,
+    };
+    let json = serde_json::to_string(&a).unwrap();
+    assert_eq!(json, r#"{"id":1}"#);
+    assert_eq!(serde_json::from_str::<EnvelopeA<String>>(&json).unwrap(), a);
+}
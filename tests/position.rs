@@ -0,0 +1,98 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde::Serialize;
+use superstruct::superstruct;
+
+#[test]
+fn position() {
+    #[superstruct(variants(A, B, C), variant_attributes(derive(Serialize)))]
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    struct Message {
+        pub w: u64,
+        pub x: u64,
+        pub y: u64,
+        #[superstruct(only(B, C), position(B = 1, C = 0))]
+        pub z: u64,
+    }
+
+    let a = Message::A(MessageA { w: 1, x: 2, y: 3 });
+    let b = Message::B(MessageB {
+        w: 1,
+        x: 2,
+        y: 3,
+        z: 4,
+    });
+    let c = Message::C(MessageC {
+        w: 1,
+        x: 2,
+        y: 3,
+        z: 4,
+    });
+
+    assert_eq!(serde_json::to_string(&a).unwrap(), r#"{"w":1,"x":2,"y":3}"#);
+    assert_eq!(
+        serde_json::to_string(&b).unwrap(),
+        r#"{"w":1,"z":4,"x":2,"y":3}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&c).unwrap(),
+        r#"{"z":4,"w":1,"x":2,"y":3}"#
+    );
+
+    // Getters and map macros are unaffected by the field order.
+    assert_eq!(*b.z().unwrap(), 4);
+    assert_eq!(
+        map_message_ref!(&'_ _, c.to_ref(), |inner, _| inner.w + inner.x),
+        3
+    );
+}
+
+#[test]
+fn position_multiple_fields() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(A, B),
+        variant_attributes(derive(Serialize))
+    )]
+    struct Block {
+        pub x: u64,
+        #[superstruct(position(B = 0))]
+        pub y: u64,
+        #[superstruct(only(B), position(B = 1))]
+        pub z: u64,
+        #[superstruct(position(Blinded = 2))]
+        pub root: u64,
+    }
+
+    let full_b = BlockFullB {
+        x: 1,
+        y: 2,
+        z: 3,
+        root: 4,
+    };
+    let blinded_b = BlockBlindedB {
+        x: 1,
+        y: 2,
+        z: 3,
+        root: 4,
+    };
+    let blinded_a = BlockBlindedA {
+        root: 4,
+        x: 1,
+        y: 2,
+    };
+
+    assert_eq!(
+        serde_json::to_string(&full_b).unwrap(),
+        r#"{"y":2,"z":3,"x":1,"root":4}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&blinded_b).unwrap(),
+        r#"{"y":2,"z":3,"root":4,"x":1}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&blinded_a).unwrap(),
+        r#"{"x":1,"y":2,"root":4}"#
+    );
+}
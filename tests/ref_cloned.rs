@@ -0,0 +1,96 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Base, Altair),
+    variant_attributes(derive(Debug, Clone, PartialEq)),
+    ref_cloned
+)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<T> {
+    body: Vec<T>,
+    #[superstruct(only(Altair))]
+    slot: u64,
+}
+
+#[test]
+fn ref_cloned() {
+    let base = Block::Base(BlockBase { body: vec![0u8] });
+    let altair = Block::Altair(BlockAltair {
+        slot: 2,
+        body: vec![1u8, 2],
+    });
+
+    for block in [&base, &altair] {
+        let block_ref = block.to_ref();
+        assert_eq!(&block_ref.cloned(), block);
+        assert_eq!(&Block::from(block_ref), block);
+    }
+
+    let owned: Block<u8> = BlockRef::from(&BlockAltair {
+        slot: 3,
+        body: vec![],
+    })
+    .into();
+    assert_eq!(owned.slot(), Ok(&3));
+}
+
+#[test]
+fn ref_cloned_meta() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(Base, Altair),
+        variant_attributes(derive(Debug, Clone, PartialEq)),
+        ref_cloned,
+        no_map_macros
+    )]
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Payload {
+        slot: u64,
+        #[superstruct(meta_only(Full))]
+        transactions: Vec<u8>,
+    }
+
+    let full = Payload::Full(PayloadFull::Altair(PayloadFullAltair {
+        slot: 1,
+        transactions: vec![4],
+    }));
+    let blinded = Payload::Blinded(PayloadBlinded::Base(PayloadBlindedBase { slot: 2 }));
+
+    for payload in [&full, &blinded] {
+        assert_eq!(&payload.to_ref().cloned(), payload);
+        assert_eq!(&Payload::from(payload.to_ref()), payload);
+    }
+
+    let PayloadRef::Full(inner) = full.to_ref() else {
+        panic!("expected a full payload");
+    };
+    assert_eq!(inner.to_ref().cloned(), *inner);
+    assert!(matches!(
+        PayloadFull::from(inner.to_ref()),
+        PayloadFull::Altair(_)
+    ));
+    let blinded_ref = PayloadBlindedRef::from(&PayloadBlindedAltair { slot: 3 });
+    assert_eq!(blinded_ref.cloned().slot(), &3);
+}
+
+#[test]
+fn ref_cloned_disabled() {
+    // Without `ref_cloned`, the conversion from `Ref` can be implemented by hand.
+    #[superstruct(variants(A, B), no_map_macros)]
+    pub struct Counter {
+        count: u64,
+    }
+
+    impl From<CounterRef<'_>> for Counter {
+        fn from(counter: CounterRef<'_>) -> Self {
+            Counter::A(CounterA {
+                count: *counter.count(),
+            })
+        }
+    }
+
+    let counter = Counter::B(CounterB { count: 1 });
+    assert!(matches!(Counter::from(counter.to_ref()), Counter::A(_)));
+}
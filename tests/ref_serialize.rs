@@ -0,0 +1,96 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde::{Deserialize, Serialize};
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Foo, Bar),
+    variant_attributes(derive(Serialize)),
+    enum_variant_attributes(serde(borrow)),
+    specific_enum_variant_attributes(Bar(serde(rename = "Baz"))),
+    ref_serialize
+)]
+#[derive(Serialize)]
+pub struct MyStruct<'a> {
+    name: &'a str,
+    #[superstruct(only(Foo))]
+    location: u16,
+}
+
+#[test]
+fn ref_serialize_external() {
+    let mut foo = MyStruct::Foo(MyStructFoo {
+        name: "foo",
+        location: 1,
+    });
+    let mut bar = MyStruct::Bar(MyStructBar { name: "bar" });
+
+    for value in [&mut foo, &mut bar] {
+        let expected = serde_json::to_string(value).unwrap();
+        assert_eq!(serde_json::to_string(&value.to_ref()).unwrap(), expected);
+        assert_eq!(serde_json::to_string(&value.to_mut()).unwrap(), expected);
+    }
+    assert_eq!(
+        serde_json::to_string(&bar.to_ref()).unwrap(),
+        r#"{"Baz":{"name":"bar"}}"#
+    );
+}
+
+#[test]
+fn ref_serialize_internal() {
+    #[superstruct(
+        variants(First, Second),
+        variant_attributes(derive(Serialize)),
+        specific_enum_variant_attributes(Second(serde(rename = "2"))),
+        ref_serialize,
+        no_map_macros
+    )]
+    #[derive(Serialize)]
+    #[serde(tag = "kind", rename_all = "lowercase")]
+    pub struct Message<T> {
+        value: T,
+        #[superstruct(only(Second))]
+        extra: bool,
+    }
+
+    let first = Message::First(MessageFirst { value: 1u8 });
+    let second = Message::Second(MessageSecond {
+        value: 2u8,
+        extra: true,
+    });
+
+    assert_eq!(
+        serde_json::to_string(&first.to_ref()).unwrap(),
+        r#"{"kind":"first","value":1}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&second.to_ref()).unwrap(),
+        serde_json::to_string(&second).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_string(&second.to_ref()).unwrap(),
+        r#"{"kind":"2","value":2,"extra":true}"#
+    );
+}
+
+#[test]
+fn ref_serialize_ignores_deserialize_attributes() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Serialize, Deserialize)),
+        specific_enum_variant_attributes(B(serde(alias = "b", rename = "Bee"))),
+        ref_serialize,
+        no_map_macros
+    )]
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields, tag = "kind")]
+    pub struct Message {
+        value: u8,
+    }
+
+    let message: Message = serde_json::from_str(r#"{"kind":"b","value":1}"#).unwrap();
+    assert_eq!(
+        serde_json::to_string(&message.to_ref()).unwrap(),
+        r#"{"kind":"Bee","value":1}"#
+    );
+}
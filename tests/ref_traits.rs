@@ -0,0 +1,105 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Base, Altair),
+    variant_attributes(derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)),
+    ref_traits(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)
+)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Block<T> {
+    slot: T,
+    #[superstruct(only(Altair))]
+    sync: bool,
+}
+
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn ref_traits_match_enum() {
+    let base = Block::Base(BlockBase { slot: 1u64 });
+    let altair = Block::Altair(BlockAltair {
+        slot: 1u64,
+        sync: true,
+    });
+
+    for block in [&base, &altair] {
+        assert_eq!(format!("{:?}", block.to_ref()), format!("{block:?}"));
+        assert_eq!(hash(&block.to_ref()), hash(block));
+        assert_eq!(block.to_ref(), block.to_ref());
+    }
+    assert_ne!(base.to_ref(), altair.to_ref());
+    assert!(base.to_ref() < altair.to_ref());
+    assert_eq!(
+        base.to_ref().cmp(&altair.to_ref()),
+        base.clone().cmp(&altair)
+    );
+    assert!(
+        Block::Base(BlockBase { slot: 0u64 }).to_ref() < base.to_ref(),
+        "variants of the same kind compare by value"
+    );
+}
+
+#[test]
+fn ref_traits_cross_equality() {
+    let inner = BlockAltair {
+        slot: 2u64,
+        sync: false,
+    };
+    let block = Block::Altair(inner.clone());
+
+    assert!(block.to_ref() == block);
+    assert!(block == block.to_ref());
+    assert!(block == inner);
+    assert!(block != BlockBase { slot: 2u64 });
+}
+
+#[test]
+fn ref_traits_single_variant() {
+    #[superstruct(
+        variants(Only),
+        variant_attributes(derive(Debug, Hash)),
+        ref_traits(Debug, Hash),
+        no_map_macros
+    )]
+    #[derive(Debug, Hash)]
+    pub struct Message {
+        value: u8,
+    }
+
+    let message = Message::Only(MessageOnly { value: 3 });
+    assert_eq!(
+        format!("{:?}", message.to_ref()),
+        "Only(MessageOnly { value: 3 })"
+    );
+    assert_eq!(hash(&message.to_ref()), hash(&message));
+}
+
+#[test]
+fn ref_traits_hash_repr() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, Hash)),
+        ref_traits(Hash),
+        no_map_macros
+    )]
+    #[derive(Debug, Hash)]
+    #[repr(u8)]
+    pub struct Message {
+        value: u8,
+    }
+
+    for message in [
+        Message::A(MessageA { value: 3 }),
+        Message::B(MessageB { value: 4 }),
+    ] {
+        assert_eq!(hash(&message.to_ref()), hash(&message));
+    }
+}
@@ -0,0 +1,68 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[test]
+fn self_means_enum() {
+    #[superstruct(
+        variants(Leaf, Node),
+        variant_attributes(derive(Debug, PartialEq, Clone))
+    )]
+    #[derive(Debug, PartialEq, Clone)]
+    struct Tree<T> {
+        value: T,
+        #[superstruct(only(Node))]
+        children: Vec<Self>,
+        #[superstruct(only(Node))]
+        parent: Option<Box<Self>>,
+    }
+
+    fn sum(tree: &Tree<u64>) -> u64 {
+        tree.value() + tree.children().map_or(0, |c| c.iter().map(sum).sum())
+    }
+
+    let leaf = Tree::Leaf(TreeLeaf { value: 1u64 });
+    let tree = Tree::Node(TreeNode {
+        value: 2,
+        children: vec![
+            leaf.clone(),
+            Tree::Node(TreeNode {
+                value: 3,
+                children: vec![leaf.clone()],
+                parent: None,
+            }),
+        ],
+        parent: Some(Box::new(leaf.clone())),
+    });
+
+    assert_eq!(sum(&tree), 7);
+    assert_eq!(tree.parent().unwrap().as_deref(), Some(&leaf));
+}
+
+#[test]
+fn self_means_variant() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq)),
+        self_means = "variant"
+    )]
+    struct List {
+        value: u64,
+        next: Option<Box<Self>>,
+    }
+
+    let a = ListA {
+        value: 1,
+        next: Some(Box::new(ListA {
+            value: 2,
+            next: None,
+        })),
+    };
+    let b: ListB = ListB {
+        value: 3,
+        next: None,
+    };
+
+    assert_eq!(a.next.as_ref().unwrap().value, 2);
+    assert_eq!(*List::B(b).value(), 3);
+}
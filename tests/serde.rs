@@ -0,0 +1,123 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde::de::DeserializeSeed;
+use serde_json::json;
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(A, B),
+    variant_attributes(derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)),
+    serde
+)]
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    common: String,
+    #[superstruct(only(B))]
+    exclusive: String,
+}
+
+#[test]
+fn deserialize_as() {
+    let value = json!({"common": "hello", "exclusive": "world"});
+
+    let a = Message::deserialize_as(MessageKind::A, &value).unwrap();
+    assert_eq!(
+        a,
+        Message::A(MessageA {
+            common: "hello".into()
+        })
+    );
+
+    let b = MessageSeed::new(MessageKind::B)
+        .deserialize(&value)
+        .unwrap();
+    assert_eq!(
+        b,
+        Message::B(MessageB {
+            common: "hello".into(),
+            exclusive: "world".into(),
+        })
+    );
+
+    assert!(Message::deserialize_as(MessageKind::B, json!({"common": "hello"})).is_err());
+}
+
+#[test]
+fn serialize() {
+    let mut b = Message::B(MessageB {
+        common: "hello".into(),
+        exclusive: "world".into(),
+    });
+    let expected = json!({"common": "hello", "exclusive": "world"});
+    assert_eq!(serde_json::to_value(&b).unwrap(), expected);
+    assert_eq!(serde_json::to_value(b.to_ref()).unwrap(), expected);
+    assert_eq!(serde_json::to_value(b.to_mut()).unwrap(), expected);
+}
+
+#[test]
+fn serde_generic() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)),
+        serde
+    )]
+    #[derive(Debug, PartialEq)]
+    pub struct Borrowed<'a, T> {
+        name: &'a str,
+        value: T,
+        #[superstruct(only(B))]
+        extra: bool,
+    }
+
+    let input = r#"{"name": "x", "value": 5, "extra": true}"#;
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let b = BorrowedSeed::<u8>::from(BorrowedKind::B)
+        .deserialize(&mut deserializer)
+        .unwrap();
+    assert_eq!(
+        b,
+        Borrowed::B(BorrowedB {
+            name: "x",
+            value: 5,
+            extra: true,
+        })
+    );
+    assert_eq!(
+        serde_json::to_string(&b).unwrap(),
+        r#"{"name":"x","value":5,"extra":true}"#
+    );
+
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let a = Borrowed::<u8>::deserialize_as(BorrowedKind::A, &mut deserializer).unwrap();
+    assert_eq!(
+        a,
+        Borrowed::A(BorrowedA {
+            name: "x",
+            value: 5
+        })
+    );
+}
+
+#[test]
+fn serde_meta_variant() {
+    #[superstruct(
+        meta_variants(Read, Write),
+        variants(Lower, Upper),
+        variant_attributes(derive(serde::Serialize, serde::Deserialize)),
+        serde
+    )]
+    pub struct Inner {
+        w: u64,
+        #[superstruct(meta_only(Read))]
+        x: u64,
+    }
+
+    let value = json!({"w": 1, "x": 2});
+    let read = InnerRead::deserialize_as(InnerReadKind::Upper, &value).unwrap();
+    assert_eq!(*read.x().unwrap(), 2);
+    assert_eq!(serde_json::to_value(Inner::Read(read)).unwrap(), value);
+    assert!(matches!(
+        InnerRead::deserialize_as(InnerReadKind::Lower, &value),
+        Ok(InnerRead::Lower(_))
+    ));
+}
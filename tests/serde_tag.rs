@@ -0,0 +1,89 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde_json::json;
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Bellatrix, Capella),
+    variant_attributes(derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)),
+    serde_tag(field = "version", rename_all = "lowercase")
+)]
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    slot: u64,
+    #[superstruct(only(Capella))]
+    withdrawals: Vec<u64>,
+}
+
+#[test]
+fn serde_tag_field() {
+    let mut block = Block::Capella(BlockCapella {
+        slot: 1,
+        withdrawals: vec![2],
+    });
+    let value = json!({"version": "capella", "slot": 1, "withdrawals": [2]});
+
+    assert_eq!(serde_json::to_value(&block).unwrap(), value);
+    assert_eq!(serde_json::to_value(block.to_ref()).unwrap(), value);
+    assert_eq!(serde_json::to_value(block.to_mut()).unwrap(), value);
+    assert_eq!(serde_json::from_value::<Block>(value).unwrap(), block);
+
+    let bellatrix: Block = serde_json::from_str(r#"{"slot": 3, "version": "bellatrix"}"#).unwrap();
+    assert_eq!(bellatrix, Block::Bellatrix(BlockBellatrix { slot: 3 }));
+
+    let error =
+        serde_json::from_value::<Block>(json!({"version": "deneb", "slot": 1})).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "unknown variant `deneb`, expected `bellatrix` or `capella`"
+    );
+}
+
+#[test]
+fn serde_tag_adjacent() {
+    #[superstruct(
+        variants(Bellatrix, Capella),
+        variant_attributes(derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)),
+        serde_tag(
+            adjacent,
+            tag = "version",
+            content = "data",
+            rename_all = "SCREAMING_SNAKE_CASE",
+            rename(Capella = "capella_v2")
+        ),
+        serde
+    )]
+    #[derive(Debug, PartialEq)]
+    pub struct Payload<T> {
+        value: T,
+        #[superstruct(only(Capella))]
+        extra: bool,
+    }
+
+    let bellatrix = Payload::Bellatrix(PayloadBellatrix { value: 1u8 });
+    let value = json!({"version": "BELLATRIX", "data": {"value": 1}});
+    assert_eq!(serde_json::to_value(&bellatrix).unwrap(), value);
+    assert_eq!(serde_json::to_value(bellatrix.to_ref()).unwrap(), value);
+    assert_eq!(
+        serde_json::from_value::<Payload<u8>>(value).unwrap(),
+        bellatrix
+    );
+
+    let capella = Payload::Capella(PayloadCapella {
+        value: 2u8,
+        extra: true,
+    });
+    let value = json!({"version": "capella_v2", "data": {"value": 2, "extra": true}});
+    assert_eq!(serde_json::to_value(&capella).unwrap(), value);
+    assert_eq!(
+        serde_json::from_value::<Payload<u8>>(value).unwrap(),
+        capella
+    );
+
+    // `deserialize_as` from the `serde` option deserializes the untagged variant struct.
+    assert_eq!(
+        Payload::<u8>::deserialize_as(PayloadKind::Bellatrix, json!({"value": 1})).unwrap(),
+        bellatrix
+    );
+    assert!(Payload::<u8>::deserialize_as(PayloadKind::Capella, json!({"value": 1})).is_err());
+}
@@ -0,0 +1,159 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::{superstruct, superstruct_impl};
+
+#[superstruct(
+    variants(A, B, C),
+    variant_attributes(derive(Debug, PartialEq)),
+    partial_getter_error(ty = "&'static str", expr = "\"wrong variant\"")
+)]
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    slot: u64,
+    #[superstruct(only(B, C))]
+    blobs: Vec<u8>,
+}
+
+#[superstruct_impl]
+impl Block {
+    /// The slot after this block's slot.
+    pub fn next_slot(&self) -> u64 {
+        self.slot + 1
+    }
+
+    #[superstruct(only(B, C))]
+    pub fn num_blobs(&self) -> usize {
+        self.blobs.len()
+    }
+
+    #[superstruct(only(B, C))]
+    pub fn add_blob(&mut self, blob: u8) {
+        self.blobs.push(blob);
+    }
+
+    #[superstruct(only(C))]
+    pub fn empty() -> Self {
+        Self {
+            slot: 0,
+            blobs: vec![],
+        }
+    }
+
+    pub fn with_slot(mut self, slot: u64) -> Self {
+        self.slot = slot;
+        self
+    }
+}
+
+#[test]
+fn superstruct_impl() {
+    let mut a = Block::A(BlockA { slot: 1 });
+    let mut c = Block::C(BlockC::empty());
+
+    assert_eq!(a.next_slot(), 2);
+    assert_eq!(BlockA { slot: 1 }.next_slot(), 2);
+    assert_eq!(a.to_ref().next_slot(), 2);
+    assert_eq!(a.num_blobs(), Err("wrong variant"));
+    assert_eq!(a.add_blob(0), Err("wrong variant"));
+
+    assert_eq!(c.add_blob(5), Ok(()));
+    assert_eq!(c.to_mut().add_blob(6), Ok(()));
+    assert_eq!(c.num_blobs(), Ok(2));
+    assert_eq!(c.to_ref().num_blobs(), Ok(2));
+    assert_eq!(c.to_mut().num_blobs(), Ok(2));
+
+    a = a.with_slot(7);
+    assert_eq!(a, Block::A(BlockA { slot: 7 }));
+    assert_eq!(BlockC::empty().with_slot(3).slot, 3);
+}
+
+#[test]
+fn superstruct_impl_generic() {
+    #[superstruct(variants(A, B), no_map_macros)]
+    pub struct Unmapped {
+        value: u8,
+    }
+
+    #[superstruct(variants(A, B))]
+    pub struct Pair<T: Clone> {
+        first: T,
+        #[superstruct(only(B))]
+        second: T,
+    }
+
+    #[superstruct_impl(only(B))]
+    impl<T: Clone> Pair<T> {
+        fn both(&self) -> (T, T) {
+            (self.first.clone(), self.second.clone())
+        }
+
+        fn convert<U: From<T>>(&self) -> U {
+            self.second.clone().into()
+        }
+    }
+
+    #[superstruct_impl]
+    impl Unmapped {
+        fn doubled(&self) -> u8 {
+            self.value * 2
+        }
+    }
+
+    let pair = Pair::B(PairB {
+        first: 1u8,
+        second: 2,
+    });
+    assert_eq!(pair.both(), Ok((1, 2)));
+    assert_eq!(pair.to_ref().convert::<u64>(), Ok(2));
+    assert_eq!(Pair::A(PairA { first: 1u8 }).both(), Err(()));
+    assert_eq!(*pair.first(), 1);
+    assert_eq!(Unmapped::A(UnmappedA { value: 1 }).doubled(), 2);
+}
+
+// The `superstruct` crate at another path, e.g. when it is re-exported by a dependency.
+mod reexport {
+    pub use ::superstruct;
+}
+
+mod headers {
+    use superstruct::superstruct;
+
+    #[superstruct(variants(A, B), crate_path = "crate::reexport::superstruct")]
+    pub struct Header {
+        pub slot: u64,
+    }
+}
+
+#[superstruct_impl]
+impl headers::Header {
+    fn is_genesis(&self) -> bool {
+        self.slot == 0
+    }
+}
+
+#[test]
+fn superstruct_impl_other_module() {
+    let header = headers::Header::B(headers::HeaderB { slot: 0 });
+    assert!(header.is_genesis());
+    assert!(!headers::HeaderA { slot: 1 }.is_genesis());
+}
+
+// The helper macro is imported along with the type, so the impl can be in any module which imports
+// the type and its variant structs, `Ref` and `RefMut`.
+mod imported {
+    use super::headers::*;
+    use superstruct::superstruct_impl;
+
+    #[superstruct_impl]
+    impl Header {
+        pub fn next_slot(&self) -> u64 {
+            self.slot + 1
+        }
+    }
+}
+
+#[test]
+fn superstruct_impl_imported_type() {
+    let header = headers::Header::A(headers::HeaderA { slot: 4 });
+    assert_eq!(header.next_slot(), 5);
+}
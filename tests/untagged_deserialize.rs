@@ -0,0 +1,146 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use serde::Deserialize;
+use serde_json::json;
+use superstruct::superstruct;
+
+#[test]
+fn most_specific_first() {
+    // Unlike `serde(untagged)`, the order of the variants doesn't matter.
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq, Deserialize)),
+        untagged_deserialize(buffer = "serde_json::Value")
+    )]
+    #[derive(Debug, PartialEq)]
+    struct Message {
+        common: String,
+        #[superstruct(only(B))]
+        exclusive: String,
+    }
+
+    let message: Message =
+        serde_json::from_str(r#"{"common": "hello", "exclusive": "world"}"#).unwrap();
+    assert_eq!(
+        message,
+        Message::B(MessageB {
+            common: "hello".into(),
+            exclusive: "world".into(),
+        })
+    );
+
+    let message: Message = serde_json::from_str(r#"{"common": "hello"}"#).unwrap();
+    assert_eq!(
+        message,
+        Message::A(MessageA {
+            common: "hello".into()
+        })
+    );
+
+    // Unknown fields are ignored without `strict`.
+    let message = Message::deserialize(json!({"common": "hello", "other": 1})).unwrap();
+    assert_eq!(
+        message,
+        Message::A(MessageA {
+            common: "hello".into()
+        })
+    );
+}
+
+#[test]
+fn strict() {
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq, Deserialize)),
+        untagged_deserialize(strict, buffer = "serde_json::Value"),
+        no_map_macros
+    )]
+    #[derive(Debug, PartialEq)]
+    struct Message {
+        #[serde(rename = "Common")]
+        common: u64,
+        #[superstruct(only(B))]
+        exclusive: Vec<u64>,
+    }
+
+    let message = Message::deserialize(json!({"Common": 1, "exclusive": [2]})).unwrap();
+    assert_eq!(
+        message,
+        Message::B(MessageB {
+            common: 1,
+            exclusive: vec![2],
+        })
+    );
+
+    let error = Message::deserialize(json!({"Common": 1, "other": 2})).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "data did not match any variant of `Message`: \
+        B: unknown field `other`, expected `Common` or `exclusive`; \
+        A: unknown field `other`, expected `Common`"
+    );
+}
+
+#[test]
+fn strict_rejects_maps() {
+    // The fields of a struct with a flattened field can't be checked, so it never matches.
+    #[superstruct(
+        variants(A, B),
+        variant_attributes(derive(Debug, PartialEq, Deserialize)),
+        untagged_deserialize(strict, buffer = "serde_json::Value"),
+        no_map_macros
+    )]
+    #[derive(Debug, PartialEq)]
+    struct Message {
+        common: u64,
+        #[superstruct(only(B), field_attributes(B(serde(flatten))))]
+        rest: std::collections::BTreeMap<String, u64>,
+    }
+
+    let message = Message::deserialize(json!({"common": 1})).unwrap();
+    assert_eq!(message, Message::A(MessageA { common: 1 }));
+
+    let error = Message::deserialize(json!({"common": 1, "other": 2})).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "data did not match any variant of `Message`: \
+        B: strict untagged deserialization requires a struct, not a map; \
+        A: unknown field `other`, expected `common`"
+    );
+}
+
+#[test]
+fn order() {
+    #[superstruct(
+        meta_variants(Read, Write),
+        variants(Lower, Upper),
+        variant_attributes(derive(Debug, PartialEq, Deserialize)),
+        untagged_deserialize(strict, order(Upper, Write), buffer = "serde_json::Value")
+    )]
+    #[derive(Debug, PartialEq)]
+    struct Inner {
+        w: u64,
+        #[superstruct(only(Lower))]
+        x: u64,
+        #[superstruct(meta_only(Read))]
+        y: u64,
+    }
+
+    // `Upper` is tried before `Lower`, even though it has fewer fields.
+    let read = InnerRead::deserialize(json!({"w": 1, "y": 2})).unwrap();
+    assert_eq!(read, InnerRead::Upper(InnerReadUpper { w: 1, y: 2 }));
+    let read = InnerRead::deserialize(json!({"w": 1, "x": 2, "y": 3})).unwrap();
+    assert_eq!(read, InnerRead::Lower(InnerReadLower { w: 1, x: 2, y: 3 }));
+
+    // `Write` is tried before `Read`.
+    let inner = Inner::deserialize(json!({"w": 1, "x": 2})).unwrap();
+    assert_eq!(
+        inner,
+        Inner::Write(InnerWrite::Lower(InnerWriteLower { w: 1, x: 2 }))
+    );
+    let inner = Inner::deserialize(json!({"w": 1, "y": 2})).unwrap();
+    assert_eq!(
+        inner,
+        Inner::Read(InnerRead::Upper(InnerReadUpper { w: 1, y: 2 }))
+    );
+}
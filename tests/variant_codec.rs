@@ -0,0 +1,199 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+/// A toy codec which encodes each field as 8 little-endian bytes.
+pub trait VariantCodec: Sized {
+    type Error;
+
+    fn decode(bytes: &[u8]) -> Result<Self, Self::Error>;
+
+    fn encode(&self) -> Vec<u8>;
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WrongLength(usize);
+
+fn decode_fields<const N: usize>(bytes: &[u8]) -> Result<[u64; N], WrongLength> {
+    if bytes.len() != N * 8 {
+        return Err(WrongLength(bytes.len()));
+    }
+    Ok(std::array::from_fn(|i| {
+        u64::from_le_bytes(bytes[i * 8..(i + 1) * 8].try_into().unwrap())
+    }))
+}
+
+fn encode_fields(fields: &[u64]) -> Vec<u8> {
+    fields
+        .iter()
+        .flat_map(|field| field.to_le_bytes())
+        .collect()
+}
+
+#[superstruct(
+    variants(Base, Altair),
+    variant_attributes(derive(Debug, PartialEq)),
+    variant_codec = "VariantCodec"
+)]
+#[derive(Debug, PartialEq)]
+pub struct State {
+    slot: u64,
+    #[superstruct(only(Altair))]
+    participation: u64,
+}
+
+impl VariantCodec for StateBase {
+    type Error = WrongLength;
+
+    fn decode(bytes: &[u8]) -> Result<Self, WrongLength> {
+        let [slot] = decode_fields(bytes)?;
+        Ok(Self { slot })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        encode_fields(&[self.slot])
+    }
+}
+
+impl VariantCodec for StateAltair {
+    type Error = WrongLength;
+
+    fn decode(bytes: &[u8]) -> Result<Self, WrongLength> {
+        let [slot, participation] = decode_fields(bytes)?;
+        Ok(Self {
+            slot,
+            participation,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        encode_fields(&[self.slot, self.participation])
+    }
+}
+
+#[test]
+fn variant_codec() {
+    let mut altair = State::Altair(StateAltair {
+        slot: 1,
+        participation: 2,
+    });
+    let bytes = altair.encode();
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(altair.to_ref().encode(), bytes);
+    assert_eq!(altair.to_mut().encode(), bytes);
+
+    assert_eq!(State::decode_as(StateKind::Altair, &bytes), Ok(altair));
+    assert_eq!(
+        State::decode_as(StateKind::Base, &bytes),
+        Err(WrongLength(16))
+    );
+    assert_eq!(
+        State::decode_as(StateKind::Base, &bytes[..8]),
+        Ok(State::Base(StateBase { slot: 1 }))
+    );
+}
+
+#[test]
+fn variant_codec_meta() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(Base, Altair),
+        variant_codec = "VariantCodec",
+        no_map_macros
+    )]
+    pub struct Block {
+        slot: u64,
+    }
+
+    macro_rules! impl_slot_codec {
+        ($($struct_name:ident),*) => {
+            $(
+                impl VariantCodec for $struct_name {
+                    type Error = WrongLength;
+
+                    fn decode(bytes: &[u8]) -> Result<Self, WrongLength> {
+                        let [slot] = decode_fields(bytes)?;
+                        Ok(Self { slot })
+                    }
+
+                    fn encode(&self) -> Vec<u8> {
+                        encode_fields(&[self.slot])
+                    }
+                }
+            )*
+        };
+    }
+    impl_slot_codec!(
+        BlockFullBase,
+        BlockFullAltair,
+        BlockBlindedBase,
+        BlockBlindedAltair
+    );
+
+    let bytes = encode_fields(&[3]);
+    let blinded = BlockBlinded::decode_as(BlockBlindedKind::Altair, &bytes).unwrap();
+    assert!(matches!(blinded, BlockBlinded::Altair(_)));
+    let block = Block::Blinded(blinded);
+    assert_eq!(block.encode(), bytes);
+    assert_eq!(block.to_ref().encode(), bytes);
+    assert_eq!(*block.slot(), 3);
+    assert!(matches!(
+        BlockBlinded::decode_as(BlockBlindedKind::Base, &bytes),
+        Ok(BlockBlinded::Base(_))
+    ));
+    for kind in BlockFullKind::ALL {
+        assert_eq!(BlockFull::decode_as(kind, &[]).err(), Some(WrongLength(0)));
+    }
+}
+
+#[test]
+fn variant_codec_meta_generic() {
+    #[superstruct(
+        meta_variants(Full, Blinded),
+        variants(Base, Altair),
+        variant_codec = "VariantCodec",
+        no_map_macros
+    )]
+    pub struct Payload<T> {
+        value: T,
+    }
+
+    macro_rules! impl_value_codec {
+        ($($struct_name:ident),*) => {
+            $(
+                impl<T: Copy + Into<u64> + TryFrom<u64>> VariantCodec for $struct_name<T> {
+                    type Error = WrongLength;
+
+                    fn decode(bytes: &[u8]) -> Result<Self, WrongLength> {
+                        let [value] = decode_fields(bytes)?;
+                        let value = T::try_from(value).map_err(|_| WrongLength(bytes.len()))?;
+                        Ok(Self { value })
+                    }
+
+                    fn encode(&self) -> Vec<u8> {
+                        encode_fields(&[self.value.into()])
+                    }
+                }
+            )*
+        };
+    }
+    impl_value_codec!(
+        PayloadFullBase,
+        PayloadFullAltair,
+        PayloadBlindedBase,
+        PayloadBlindedAltair
+    );
+
+    fn encode_payload<T: Copy + Into<u64> + TryFrom<u64>>(payload: &Payload<T>) -> Vec<u8> {
+        payload.encode()
+    }
+
+    let bytes = encode_fields(&[5]);
+    let payload = Payload::Full(PayloadFull::decode_as(PayloadFullKind::Altair, &bytes).unwrap());
+    assert_eq!(encode_payload::<u8>(&payload), bytes);
+    assert_eq!(payload.to_ref().encode(), bytes);
+    assert!(matches!(
+        PayloadFull::<u8>::decode_as(PayloadFullKind::Base, &bytes),
+        Ok(PayloadFull::Base(_))
+    ));
+}
@@ -0,0 +1,97 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Base, Ext),
+    variant_attributes(derive(Debug, PartialEq)),
+    variant_trait
+)]
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    id: u64,
+    #[superstruct(only(Ext))]
+    name: String,
+}
+
+#[test]
+fn variant_kind() {
+    let base = Record::Base(RecordBase { id: 1 });
+    let mut ext = Record::Ext(RecordExt {
+        id: 2,
+        name: "ext".into(),
+    });
+
+    assert_eq!(base.variant_kind(), RecordKind::Base);
+    assert_eq!(ext.to_ref().variant_kind(), RecordKind::Ext);
+    assert_eq!(ext.to_mut().variant_kind(), RecordKind::Ext);
+    assert_eq!(RecordKind::ALL, [RecordKind::Base, RecordKind::Ext]);
+    assert_eq!(RecordExt::KIND, RecordKind::Ext);
+
+    fn kind_of<V: RecordVariant>(_: &V) -> RecordKind {
+        V::KIND
+    }
+    assert_eq!(kind_of(&RecordBase { id: 0 }), RecordKind::Base);
+
+    fn to_record<V: RecordVariant>(variant: V) -> Record {
+        variant.into()
+    }
+    assert_eq!(
+        to_record(RecordBase { id: 3 }),
+        Record::Base(RecordBase { id: 3 })
+    );
+}
+
+#[test]
+fn try_get() {
+    let base = Record::Base(RecordBase { id: 1 });
+    let mut ext = Record::Ext(RecordExt {
+        id: 2,
+        name: "ext".into(),
+    });
+
+    assert_eq!(base.try_get::<RecordBase>(), Some(&RecordBase { id: 1 }));
+    assert_eq!(base.try_get::<RecordExt>(), None);
+    assert_eq!(base.to_ref().try_get::<RecordBase>().unwrap().id, 1);
+
+    ext.try_get_mut::<RecordExt>().unwrap().name.push('!');
+    ext.to_mut().try_get_mut::<RecordExt>().unwrap().id += 1;
+    assert!(ext.to_mut().try_get_mut::<RecordBase>().is_none());
+    assert_eq!(ext.name().unwrap(), "ext!");
+    assert_eq!(*ext.id(), 3);
+
+    let ext = RecordExt::from_enum(ext).unwrap();
+    assert_eq!(
+        RecordBase::from_enum(ext.into_enum())
+            .unwrap_err()
+            .variant_kind(),
+        RecordKind::Ext
+    );
+}
+
+#[test]
+fn variant_kind_only() {
+    // Without `variant_kind` or `variant_trait`, their names are free for fields and types.
+    #[superstruct(variants(A, B))]
+    pub struct Lookup {
+        variant_kind: u8,
+        try_get: u8,
+    }
+
+    #[allow(dead_code)]
+    pub struct LookupVariant;
+
+    #[superstruct(variants(A, B), variant_kind)]
+    pub struct Tagged {
+        id: u8,
+    }
+
+    let lookup = Lookup::A(LookupA {
+        variant_kind: 1,
+        try_get: 2,
+    });
+    assert_eq!(lookup.variant_kind() + lookup.try_get(), 3);
+    let tagged = Tagged::B(TaggedB { id: 4 });
+    assert_eq!(tagged.variant_kind(), TaggedKind::B);
+    assert_eq!(*tagged.id(), 4);
+}
@@ -0,0 +1,152 @@
+#![allow(non_local_definitions)] // for macros on structs within test functions
+
+use superstruct::superstruct;
+
+#[superstruct(
+    variants(Circle, Square),
+    variant_attributes(derive(Debug, PartialEq)),
+    visitors
+)]
+#[derive(Debug, PartialEq)]
+pub struct Shape {
+    size: u64,
+    #[superstruct(only(Circle))]
+    name: String,
+}
+
+struct Describe;
+
+impl ShapeVisitor for Describe {
+    type Output = String;
+
+    fn visit_any<V: ShapeVariant>(&mut self, _: &V) -> String {
+        "shape".into()
+    }
+
+    fn visit_circle(&mut self, circle: &ShapeCircle) -> String {
+        format!("circle {}", circle.name)
+    }
+}
+
+struct Grow(u64);
+
+impl ShapeVisitorMut for Grow {
+    type Output = ();
+
+    fn visit_any_mut<V: ShapeVariant>(&mut self, _: &mut V) {}
+
+    fn visit_circle_mut(&mut self, circle: &mut ShapeCircle) {
+        circle.size += self.0;
+    }
+
+    fn visit_square_mut(&mut self, square: &mut ShapeSquare) {
+        square.size *= self.0;
+    }
+}
+
+#[test]
+fn visitor() {
+    let mut circle = Shape::Circle(ShapeCircle {
+        size: 1,
+        name: "c".into(),
+    });
+    let mut square = Shape::Square(ShapeSquare { size: 2 });
+
+    assert_eq!(circle.accept(&mut Describe), "circle c");
+    assert_eq!(square.accept(&mut Describe), "shape");
+    assert_eq!(circle.to_ref().accept(&mut Describe), "circle c");
+    assert_eq!(square.to_mut().accept(&mut Describe), "shape");
+
+    circle.accept_mut(&mut Grow(3));
+    square.to_mut().accept_mut(&mut Grow(3));
+    assert_eq!(*circle.size(), 4);
+    assert_eq!(*square.size(), 6);
+}
+
+#[test]
+fn folder() {
+    struct Round;
+
+    impl ShapeFolder for Round {
+        type Output = Shape;
+
+        fn fold_any<V: ShapeVariant>(&mut self, variant: V) -> Shape {
+            variant.into_enum()
+        }
+
+        fn fold_square(&mut self, square: ShapeSquare) -> Shape {
+            Shape::Circle(ShapeCircle {
+                size: square.size,
+                name: "square".into(),
+            })
+        }
+    }
+
+    let circle = ShapeCircle {
+        size: 1,
+        name: "c".into(),
+    };
+    assert_eq!(
+        Shape::Circle(circle).fold(&mut Round),
+        Shape::Circle(ShapeCircle {
+            size: 1,
+            name: "c".into(),
+        })
+    );
+    assert_eq!(
+        Shape::Square(ShapeSquare { size: 2 }).fold(&mut Round),
+        Shape::Circle(ShapeCircle {
+            size: 2,
+            name: "square".into(),
+        })
+    );
+}
+
+#[test]
+fn visitor_generic() {
+    #[superstruct(variants(A, B), visitors)]
+    pub struct Wrapper<'a, V> {
+        value: &'a V,
+        #[superstruct(only(B))]
+        extra: u8,
+    }
+
+    struct Count(usize);
+
+    impl<'a, V: 'a> WrapperVisitor<'a, V> for Count {
+        type Output = ();
+
+        fn visit_any<V_: WrapperVariant<'a, V>>(&mut self, _: &V_) {
+            self.0 += 1;
+        }
+    }
+
+    let value = 5;
+    let mut count = Count(0);
+    Wrapper::A(WrapperA { value: &value }).accept(&mut count);
+    let b = Wrapper::B(WrapperB {
+        value: &value,
+        extra: 0,
+    });
+    b.to_ref().accept(&mut count);
+    assert_eq!(count.0, 2);
+    assert_eq!(**b.value(), 5);
+    assert_eq!(*b.extra().unwrap(), 0);
+}
+
+#[test]
+fn no_visitors() {
+    // Without `visitors`, the names of the visitor methods are free for fields and methods.
+    #[superstruct(variants(A, B))]
+    pub struct Reduce {
+        fold: u64,
+    }
+
+    impl Reduce {
+        fn accept(&self) -> bool {
+            *self.fold() > 0
+        }
+    }
+
+    assert!(Reduce::B(ReduceB { fold: 1 }).accept());
+}